@@ -1,8 +1,11 @@
 /// Demonstrates rendering image font text at both its 'native' height and a
-/// scaled-up height.
+/// scaled-up height. The text is snapped to the pixel grid so that it stays
+/// crisp.
 use bevy::prelude::*;
 use bevy_asset_loader::prelude::{AssetCollection, AssetCollectionApp};
-use extol_image_font::{ImageFont, ImageFontBundle, ImageFontPlugin, ImageFontText};
+use extol_image_font::{
+    snap::PixelSnap, ImageFont, ImageFontBundle, ImageFontPlugin, ImageFontText,
+};
 
 fn main() {
     App::new()
@@ -22,24 +25,27 @@ struct DemoAssets {
 fn spawn_text(mut commands: Commands, assets: Res<DemoAssets>) {
     commands.spawn(Camera2dBundle::default());
 
-    // XXX: shouldn't be exactly on integer coordinates. not sure why.
-    commands.spawn(ImageFontBundle {
-        text: ImageFontText::default()
-            .text("Sphinx of black quartz, judge my vow!")
-            .font(assets.image_font.clone())
-            .font_height(36.0),
-        sprite: SpriteBundle {
-            transform: Transform::from_translation(Vec3::new(0.2, 0.2, 0.2)),
-            ..default()
-        },
-    });
-    commands.spawn(ImageFontBundle {
-        text: ImageFontText::default()
-            .text("Sphinx of black quartz, judge my vow!")
-            .font(assets.image_font.clone()),
-        sprite: SpriteBundle {
-            transform: Transform::from_translation(Vec3::new(0.2, 40.2, 0.2)),
-            ..default()
-        },
-    });
+    commands
+        .spawn(ImageFontBundle {
+            text: ImageFontText::default()
+                .text("Sphinx of black quartz, judge my vow!")
+                .font(assets.image_font.clone())
+                .font_height(36.0),
+            sprite: SpriteBundle {
+                transform: Transform::from_translation(Vec3::ZERO),
+                ..default()
+            },
+        })
+        .insert(PixelSnap::default());
+    commands
+        .spawn(ImageFontBundle {
+            text: ImageFontText::default()
+                .text("Sphinx of black quartz, judge my vow!")
+                .font(assets.image_font.clone()),
+            sprite: SpriteBundle {
+                transform: Transform::from_translation(Vec3::new(0.0, 40.0, 0.0)),
+                ..default()
+            },
+        })
+        .insert(PixelSnap::default());
 }
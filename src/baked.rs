@@ -0,0 +1,625 @@
+//! Baking fonts into a compact binary format with Bevy's asset processor.
+//!
+//! When asset processing is enabled, fonts loaded by any of this crate's
+//! loaders are saved by [`BakedImageFontSaver`] and loaded in the final
+//! application by [`BakedImageFontLoader`]. The baked file contains the final
+//! glyph table along with the textures as embedded PNGs, so loading it doesn't
+//! involve parsing RON or slicing up the image, and problems with the font
+//! (such as glyphs that lie outside their texture) are reported when the asset
+//! is processed rather than when it's loaded.
+//!
+//! Optionally, the saver can trim transparent borders off every glyph and
+//! repack them into new textures; see [`BakedImageFontSaverSettings`].
+use std::io::Cursor;
+
+use bevy::{
+    asset::{
+        io::{Reader, Writer},
+        processor::LoadAndSave,
+        saver::{AssetSaver, SavedAsset},
+        AssetLoader, AsyncReadExt, AsyncWriteExt, LoadContext,
+    },
+    prelude::*,
+    render::render_asset::RenderAssetUsages,
+    utils::{BoxedFuture, HashMap},
+};
+use image::{DynamicImage, ImageError, ImageFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    bmfont::BmFontLoader,
+    loader::{page_label, ImageFontLoadError, ImageFontLoader, ImageFontLoaderSettings},
+    packing, GlyphMetrics, ImageFont,
+};
+
+const MAGIC: &[u8; 4] = b"EIFB";
+const VERSION: u8 = 1;
+
+/// Settings for [`BakedImageFontSaver`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BakedImageFontSaverSettings {
+    /// If set, trims the transparent border off every glyph (adjusting its
+    /// offset to compensate) and repacks the glyphs into new textures. This
+    /// usually makes the textures much smaller, but means the texture no
+    /// longer looks like the original image.
+    pub repack: bool,
+}
+
+/// Errors that can show up when baking a font.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum BakeError {
+    #[error("i/o error when saving baked font: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("texture for page {0} is missing")]
+    MissingTexture(usize),
+    #[error("failed to convert image to DynamicImage: {0}")]
+    ImageConversion(String),
+    #[error("failed to encode texture: {0}")]
+    Encode(#[from] ImageError),
+    #[error("glyph {index} (for {keys:?}) is on page {page}, which doesn't exist")]
+    InvalidPage {
+        index: usize,
+        keys: Vec<String>,
+        page: usize,
+    },
+    #[error(
+        "glyph {index} (for {keys:?}) has rect {rect:?}, which is outside its {page_size} texture"
+    )]
+    GlyphOutOfBounds {
+        index: usize,
+        keys: Vec<String>,
+        rect: Rect,
+        page_size: UVec2,
+    },
+    #[error(
+        "glyph {index} (for {keys:?}) is {size}, which is bigger than the largest texture page \
+         ({max}x{max})",
+        max = packing::MAX_PAGE_SIZE
+    )]
+    GlyphTooLarge {
+        index: usize,
+        keys: Vec<String>,
+        size: UVec2,
+    },
+    #[error("{key:?} maps to glyph {index}, which doesn't exist")]
+    InvalidGlyphIndex { key: String, index: usize },
+}
+
+/// Errors that can show up when reading a baked font.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum BakedImageFontParseError {
+    #[error("not a baked image font")]
+    BadMagic,
+    #[error("unsupported baked image font version {0}")]
+    UnsupportedVersion(u8),
+    #[error("baked image font ended unexpectedly")]
+    UnexpectedEof,
+    #[error("invalid codepoint {0:#x}")]
+    InvalidCodepoint(u32),
+    #[error("glyph key is not valid UTF-8")]
+    NotUtf8,
+    #[error("glyph {index} is on page {page}, but there are only {pages} pages")]
+    InvalidPage {
+        index: usize,
+        page: usize,
+        pages: usize,
+    },
+    #[error("glyph {index} has rect {rect:?}, which is outside its {page_size} page")]
+    GlyphOutOfBounds {
+        index: usize,
+        rect: URect,
+        page_size: UVec2,
+    },
+    #[error("{key:?} maps to glyph {index}, but there are only {glyphs} glyphs")]
+    InvalidGlyphIndex {
+        key: String,
+        index: usize,
+        glyphs: usize,
+    },
+    #[error("failed to decode texture: {0}")]
+    Decode(#[from] ImageError),
+}
+
+/// A single glyph in a [`BakedFont`].
+#[derive(Debug, Clone, Copy)]
+struct BakedGlyph {
+    page: usize,
+    rect: URect,
+    metrics: GlyphMetrics,
+}
+
+/// Everything in an [`ImageFont`] that ends up in the baked file.
+struct BakedFont {
+    line_height: u32,
+    pages: Vec<RgbaImage>,
+    glyphs: Vec<BakedGlyph>,
+    index_map: Vec<(String, usize)>,
+    kerning: Vec<((char, char), i32)>,
+    mark_offsets: Vec<(char, IVec2)>,
+}
+
+impl BakedFont {
+    /// Collects the information from the font, checking that it all makes
+    /// sense.
+    fn new(font: &ImageFont, pages: Vec<RgbaImage>) -> Result<Self, BakeError> {
+        let keys_for = |index: usize| -> Vec<String> {
+            let mut keys: Vec<String> = font
+                .index_map
+                .iter()
+                .filter(|(_, &i)| i == index)
+                .map(|(key, _)| key.clone())
+                .collect();
+            keys.sort();
+            keys
+        };
+
+        let mut glyphs = vec![];
+        for (index, (&rect, (&page, &metrics))) in font
+            .layout
+            .textures
+            .iter()
+            .zip(font.pages.iter().zip(&font.metrics))
+            .enumerate()
+        {
+            let Some(texture) = pages.get(page) else {
+                return Err(BakeError::InvalidPage {
+                    index,
+                    keys: keys_for(index),
+                    page,
+                });
+            };
+            let page_size = UVec2::from(texture.dimensions());
+            let urect =
+                URect::from_corners(rect.min.floor().as_uvec2(), rect.max.ceil().as_uvec2());
+            if rect.min.cmplt(Vec2::ZERO).any() || urect.max.cmpgt(page_size).any() {
+                return Err(BakeError::GlyphOutOfBounds {
+                    index,
+                    keys: keys_for(index),
+                    rect,
+                    page_size,
+                });
+            }
+            glyphs.push(BakedGlyph {
+                page,
+                rect: urect,
+                metrics,
+            });
+        }
+
+        let mut index_map: Vec<_> = font
+            .index_map
+            .iter()
+            .map(|(key, &i)| (key.clone(), i))
+            .collect();
+        index_map.sort();
+        if let Some((key, index)) = index_map.iter().find(|(_, i)| *i >= glyphs.len()) {
+            return Err(BakeError::InvalidGlyphIndex {
+                key: key.clone(),
+                index: *index,
+            });
+        }
+        let mut kerning: Vec<_> = font.kerning.iter().map(|(&k, &v)| (k, v)).collect();
+        kerning.sort();
+        let mut mark_offsets: Vec<_> = font.mark_offsets.iter().map(|(&c, &o)| (c, o)).collect();
+        mark_offsets.sort_by_key(|&(c, _)| c);
+
+        Ok(Self {
+            line_height: font.line_height,
+            pages,
+            glyphs,
+            index_map,
+            kerning,
+            mark_offsets,
+        })
+    }
+
+    /// Trims the transparent border off each glyph and repacks them all into
+    /// new pages.
+    fn repacked(self) -> Result<Self, BakeError> {
+        let trimmed: Vec<(URect, BakedGlyph)> = self
+            .glyphs
+            .iter()
+            .map(|glyph| {
+                let page = &self.pages[glyph.page];
+                let mut min = glyph.rect.max;
+                let mut max = glyph.rect.min;
+                for y in glyph.rect.min.y..glyph.rect.max.y {
+                    for x in glyph.rect.min.x..glyph.rect.max.x {
+                        if page.get_pixel(x, y)[3] != 0 {
+                            min = min.min(UVec2::new(x, y));
+                            max = max.max(UVec2::new(x + 1, y + 1));
+                        }
+                    }
+                }
+                let trimmed = if min.cmplt(max).all() {
+                    URect::from_corners(min, max)
+                } else {
+                    URect::from_corners(glyph.rect.min, glyph.rect.min)
+                };
+                (trimmed, *glyph)
+            })
+            .collect();
+
+        let sizes: Vec<UVec2> = trimmed.iter().map(|(rect, _)| rect.size()).collect();
+        let packing = packing::pack(&sizes).map_err(|index| BakeError::GlyphTooLarge {
+            index,
+            keys: self
+                .index_map
+                .iter()
+                .filter(|(_, i)| *i == index)
+                .map(|(key, _)| key.clone())
+                .collect(),
+            size: sizes[index],
+        })?;
+        let mut pages: Vec<RgbaImage> = packing
+            .page_sizes
+            .iter()
+            .map(|size| RgbaImage::new(size.x, size.y))
+            .collect();
+        let glyphs = trimmed
+            .into_iter()
+            .zip(packing.positions)
+            .map(|((trimmed, glyph), (page, position))| {
+                let source = &self.pages[glyph.page];
+                for y in 0..trimmed.height() {
+                    for x in 0..trimmed.width() {
+                        let pixel = *source.get_pixel(trimmed.min.x + x, trimmed.min.y + y);
+                        pages[page].put_pixel(position.x + x, position.y + y, pixel);
+                    }
+                }
+                BakedGlyph {
+                    page,
+                    rect: URect::from_corners(position, position + trimmed.size()),
+                    metrics: GlyphMetrics {
+                        offset: glyph.metrics.offset
+                            + (trimmed.min.as_ivec2() - glyph.rect.min.as_ivec2()),
+                        ..glyph.metrics
+                    },
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            pages,
+            glyphs,
+            ..self
+        })
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, BakeError> {
+        let mut out = MAGIC.to_vec();
+        out.push(VERSION);
+        put_u32(&mut out, self.line_height);
+
+        put_len(&mut out, self.pages.len());
+        for page in &self.pages {
+            let mut png = vec![];
+            page.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+            put_len(&mut out, png.len());
+            out.extend(png);
+        }
+
+        put_len(&mut out, self.glyphs.len());
+        for glyph in &self.glyphs {
+            put_len(&mut out, glyph.page);
+            for value in [glyph.rect.min, glyph.rect.max] {
+                put_u32(&mut out, value.x);
+                put_u32(&mut out, value.y);
+            }
+            put_i32(&mut out, glyph.metrics.offset.x);
+            put_i32(&mut out, glyph.metrics.offset.y);
+            put_i32(&mut out, glyph.metrics.advance);
+            put_i32(&mut out, glyph.metrics.vertical_advance);
+        }
+
+        put_len(&mut out, self.index_map.len());
+        for (key, index) in &self.index_map {
+            put_len(&mut out, key.len());
+            out.extend(key.as_bytes());
+            put_len(&mut out, *index);
+        }
+
+        put_len(&mut out, self.kerning.len());
+        for &((left, right), amount) in &self.kerning {
+            put_u32(&mut out, left.into());
+            put_u32(&mut out, right.into());
+            put_i32(&mut out, amount);
+        }
+
+        put_len(&mut out, self.mark_offsets.len());
+        for &(c, offset) in &self.mark_offsets {
+            put_u32(&mut out, c.into());
+            put_i32(&mut out, offset.x);
+            put_i32(&mut out, offset.y);
+        }
+        Ok(out)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, BakedImageFontParseError> {
+        let mut data = ByteReader(
+            bytes
+                .strip_prefix(MAGIC)
+                .ok_or(BakedImageFontParseError::BadMagic)?,
+        );
+        let version = data.take(1)?[0];
+        if version != VERSION {
+            return Err(BakedImageFontParseError::UnsupportedVersion(version));
+        }
+        let line_height = data.u32()?;
+
+        let pages: Vec<RgbaImage> = (0..data.u32()?)
+            .map(|_| {
+                let len = data.u32()? as usize;
+                let png = data.take(len)?;
+                Ok(image::load_from_memory_with_format(png, ImageFormat::Png)?.to_rgba8())
+            })
+            .collect::<Result<_, BakedImageFontParseError>>()?;
+
+        let glyphs: Vec<BakedGlyph> = (0..data.u32()?)
+            .map(|_| {
+                Ok(BakedGlyph {
+                    page: data.u32()? as usize,
+                    rect: URect::new(data.u32()?, data.u32()?, data.u32()?, data.u32()?),
+                    metrics: GlyphMetrics {
+                        offset: IVec2::new(data.i32()?, data.i32()?),
+                        advance: data.i32()?,
+                        vertical_advance: data.i32()?,
+                    },
+                })
+            })
+            .collect::<Result<_, BakedImageFontParseError>>()?;
+        // a corrupt file mustn't make rendering panic later on
+        for (index, glyph) in glyphs.iter().enumerate() {
+            let Some(page) = pages.get(glyph.page) else {
+                return Err(BakedImageFontParseError::InvalidPage {
+                    index,
+                    page: glyph.page,
+                    pages: pages.len(),
+                });
+            };
+            let page_size = UVec2::from(page.dimensions());
+            if glyph.rect.max.cmpgt(page_size).any() {
+                return Err(BakedImageFontParseError::GlyphOutOfBounds {
+                    index,
+                    rect: glyph.rect,
+                    page_size,
+                });
+            }
+        }
+
+        let index_map: Vec<(String, usize)> = (0..data.u32()?)
+            .map(|_| Ok((data.string()?, data.u32()? as usize)))
+            .collect::<Result<_, BakedImageFontParseError>>()?;
+        if let Some((key, index)) = index_map.iter().find(|(_, index)| *index >= glyphs.len()) {
+            return Err(BakedImageFontParseError::InvalidGlyphIndex {
+                key: key.clone(),
+                index: *index,
+                glyphs: glyphs.len(),
+            });
+        }
+
+        let kerning = (0..data.u32()?)
+            .map(|_| Ok(((data.char()?, data.char()?), data.i32()?)))
+            .collect::<Result<_, BakedImageFontParseError>>()?;
+
+        let mark_offsets = (0..data.u32()?)
+            .map(|_| Ok((data.char()?, IVec2::new(data.i32()?, data.i32()?))))
+            .collect::<Result<_, BakedImageFontParseError>>()?;
+
+        Ok(Self {
+            line_height,
+            pages,
+            glyphs,
+            index_map,
+            kerning,
+            mark_offsets,
+        })
+    }
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend(value.to_le_bytes());
+}
+
+fn put_i32(out: &mut Vec<u8>, value: i32) {
+    out.extend(value.to_le_bytes());
+}
+
+fn put_len(out: &mut Vec<u8>, len: usize) {
+    put_u32(out, len.try_into().expect("font is too big to bake"));
+}
+
+/// Little-endian reader for the baked format.
+struct ByteReader<'a>(&'a [u8]);
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], BakedImageFontParseError> {
+        if self.0.len() < n {
+            return Err(BakedImageFontParseError::UnexpectedEof);
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, BakedImageFontParseError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> Result<i32, BakedImageFontParseError> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, BakedImageFontParseError> {
+        let len = self.u32()? as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| BakedImageFontParseError::NotUtf8)
+    }
+
+    fn char(&mut self) -> Result<char, BakedImageFontParseError> {
+        let value = self.u32()?;
+        char::from_u32(value).ok_or(BakedImageFontParseError::InvalidCodepoint(value))
+    }
+}
+
+/// Saves an [`ImageFont`] in the baked format. Loader settings that end up in
+/// the font (such as its sampler) are carried over to the baked asset's
+/// [`ImageFontLoaderSettings`].
+#[derive(Debug, Default)]
+pub struct BakedImageFontSaver;
+
+impl AssetSaver for BakedImageFontSaver {
+    type Asset = ImageFont;
+
+    type Settings = BakedImageFontSaverSettings;
+
+    type OutputLoader = BakedImageFontLoader;
+
+    type Error = BakeError;
+
+    fn save<'a>(
+        &'a self,
+        writer: &'a mut Writer,
+        asset: SavedAsset<'a, Self::Asset>,
+        settings: &'a Self::Settings,
+    ) -> BoxedFuture<'a, Result<ImageFontLoaderSettings, Self::Error>> {
+        Box::pin(async move {
+            let mut pages = vec![];
+            let mut texture_usage = RenderAssetUsages::default();
+            for i in 0..asset.textures.len() {
+                let texture = asset
+                    .get_labeled::<Image, _>(page_label(i).as_str())
+                    .ok_or(BakeError::MissingTexture(i))?;
+                texture_usage = texture.asset_usage;
+                let texture = texture
+                    .get()
+                    .clone()
+                    .try_into_dynamic()
+                    .map_err(|e| BakeError::ImageConversion(e.to_string()))?;
+                pages.push(texture.to_rgba8());
+            }
+
+            let mut baked = BakedFont::new(&asset, pages)?;
+            if settings.repack {
+                baked = baked.repacked()?;
+            }
+            writer.write_all(&baked.to_bytes()?).await?;
+
+            Ok(ImageFontLoaderSettings {
+                sampler: asset.sampler.clone(),
+                font_height: asset.default_font_height,
+                missing_glyph: asset.missing_glyph,
+                texture_usage,
+                ..default()
+            })
+        })
+    }
+}
+
+/// Loader for [`ImageFont`]s produced by [`BakedImageFontSaver`].
+#[derive(Debug, Default)]
+pub struct BakedImageFontLoader;
+
+impl AssetLoader for BakedImageFontLoader {
+    type Asset = ImageFont;
+
+    type Settings = ImageFontLoaderSettings;
+
+    type Error = ImageFontLoadError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = vec![];
+            reader.read_to_end(&mut bytes).await?;
+            let baked = BakedFont::from_bytes(&bytes)?;
+            Ok(baked.into_image_font(load_context, settings))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["image_font.baked"]
+    }
+}
+
+impl BakedFont {
+    fn into_image_font(
+        self,
+        load_context: &mut LoadContext,
+        settings: &ImageFontLoaderSettings,
+    ) -> ImageFont {
+        let max_size = self
+            .pages
+            .iter()
+            .map(|page| UVec2::from(page.dimensions()))
+            .fold(UVec2::ZERO, UVec2::max);
+        let textures = self
+            .pages
+            .into_iter()
+            .enumerate()
+            .map(|(i, page)| {
+                let mut image = Image::from_dynamic(
+                    DynamicImage::ImageRgba8(page),
+                    true,
+                    settings.texture_usage,
+                );
+                settings.apply_to_texture(&mut image);
+                load_context.add_labeled_asset(page_label(i), image)
+            })
+            .collect();
+
+        let mut layout = TextureAtlasLayout::new_empty(max_size.as_vec2());
+        let mut pages = vec![];
+        let mut metrics = vec![];
+        for glyph in self.glyphs {
+            layout.add_texture(glyph.rect.as_rect());
+            pages.push(glyph.page);
+            metrics.push(glyph.metrics);
+        }
+        let mut font = ImageFont {
+            layout,
+            textures,
+            pages,
+            index_map: self.index_map.into_iter().collect::<HashMap<_, _>>(),
+            max_key_len: 0,
+            metrics,
+            kerning: self.kerning.into_iter().collect(),
+            mark_offsets: self.mark_offsets.into_iter().collect(),
+            line_height: self.line_height,
+            sampler: settings.sampler.clone(),
+            default_font_height: settings.font_height,
+            missing_glyph: settings.missing_glyph,
+        };
+        // the keys were normalized before baking
+        font.update_max_key_len();
+        font
+    }
+}
+
+/// Processes `.image_font.ron` files into baked fonts.
+pub type ImageFontProcessor = LoadAndSave<ImageFontLoader, BakedImageFontSaver>;
+/// Processes BMFont descriptors into baked fonts.
+pub type BmFontProcessor = LoadAndSave<BmFontLoader, BakedImageFontSaver>;
+/// Processes BDF fonts into baked fonts.
+#[cfg(feature = "bdf")]
+pub type BdfProcessor = LoadAndSave<crate::bdf::BdfLoader, BakedImageFontSaver>;
+
+/// Registers the processors and makes them the default for their extensions.
+/// This does nothing if the asset processor isn't running.
+pub(crate) fn register_processors(app: &mut App) {
+    app.register_asset_processor(ImageFontProcessor::from(BakedImageFontSaver))
+        .set_default_asset_processor::<ImageFontProcessor>("image_font.ron")
+        .register_asset_processor(BmFontProcessor::from(BakedImageFontSaver))
+        .set_default_asset_processor::<BmFontProcessor>("fnt");
+    #[cfg(feature = "bdf")]
+    app.register_asset_processor(BdfProcessor::from(BakedImageFontSaver))
+        .set_default_asset_processor::<BdfProcessor>("bdf");
+}
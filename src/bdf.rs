@@ -0,0 +1,411 @@
+//! Code for loading an [`ImageFont`] from a [BDF] bitmap font. Unlike the
+//! other formats, BDF fonts don't come with an image; the glyph bitmaps are
+//! rasterized into freshly generated atlas pages instead, so even fonts with
+//! tens of thousands of glyphs like Unifont can be used. Set pixels are opaque
+//! white, so use the sprite or image color to tint the text.
+//!
+//! Requires the `bdf` feature.
+//!
+//! [BDF]: https://en.wikipedia.org/wiki/Glyph_Bitmap_Distribution_Format
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    utils::{BoxedFuture, HashMap},
+};
+use thiserror::Error;
+
+use crate::{
+    loader::{page_label, ImageFontLoadError, ImageFontLoaderSettings},
+    packing, GlyphMetrics, ImageFont,
+};
+
+/// The parts of a BDF font that are relevant to rendering.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BdfFont {
+    /// Distance from the top of a line to the baseline.
+    pub ascent: i32,
+    /// Distance from the baseline to the bottom of a line.
+    pub descent: i32,
+    pub glyphs: Vec<BdfGlyph>,
+}
+
+/// A single glyph in a BDF font.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BdfGlyph {
+    pub c: char,
+    /// Horizontal advance (`DWIDTH`).
+    pub advance: i32,
+    /// Vertical advance, if the font has vertical metrics. This is the second
+    /// value of `DWIDTH1`, negated so that it's positive for text running
+    /// down.
+    pub vertical_advance: Option<i32>,
+    /// Size of the bitmap (the first two values of `BBX`).
+    pub size: UVec2,
+    /// Offset of the bitmap's bottom-left corner from the origin, with y
+    /// pointing up (the last two values of `BBX`).
+    pub offset: IVec2,
+    /// Whether each pixel is set, row by row from the top.
+    pub bitmap: Vec<bool>,
+}
+
+/// Errors that can show up when parsing a BDF font.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum BdfParseError {
+    #[error("font is not valid UTF-8: {0}")]
+    NotUtf8(#[from] std::str::Utf8Error),
+    #[error("line {line}: expected {expected} numbers after `{keyword}`")]
+    BadValues {
+        line: usize,
+        keyword: &'static str,
+        expected: usize,
+    },
+    #[error("line {line}: invalid bitmap row {row:?}")]
+    BadBitmapRow { line: usize, row: String },
+    #[error("glyph {name:?} has no `BBX`")]
+    MissingBoundingBox { name: String },
+    #[error("font has neither `FONT_ASCENT` nor `FONTBOUNDINGBOX`")]
+    MissingAscent,
+    #[error(
+        "line {line}: glyph {name:?} is {width}x{height}, which is bigger than the largest \
+         texture page ({max}x{max})",
+        max = packing::MAX_PAGE_SIZE
+    )]
+    GlyphTooLarge {
+        line: usize,
+        name: String,
+        width: i32,
+        height: i32,
+    },
+}
+
+impl BdfFont {
+    /// Parses the contents of a `.bdf` file.
+    ///
+    /// ```rust
+    /// # use extol_image_font::bdf::*;
+    /// let s = "\
+    /// STARTFONT 2.1
+    /// FONT -example-fixed-medium-r-normal--4-40-75-75-c-40-iso10646-1
+    /// SIZE 4 75 75
+    /// FONTBOUNDINGBOX 4 4 0 -1
+    /// STARTPROPERTIES 2
+    /// FONT_ASCENT 3
+    /// FONT_DESCENT 1
+    /// ENDPROPERTIES
+    /// CHARS 1
+    /// STARTCHAR exclam
+    /// ENCODING 33
+    /// SWIDTH 500 0
+    /// DWIDTH 4 0
+    /// BBX 1 3 1 0
+    /// BITMAP
+    /// 80
+    /// 00
+    /// 80
+    /// ENDCHAR
+    /// ENDFONT
+    /// ";
+    /// let font = BdfFont::parse(s.as_bytes()).unwrap();
+    /// assert_eq!((font.ascent, font.descent), (3, 1));
+    /// assert_eq!(font.glyphs[0].c, '!');
+    /// assert_eq!(font.glyphs[0].bitmap, vec![true, false, true]);
+    ///
+    /// // glyphs that couldn't fit in a texture page are rejected up front
+    /// let huge = s.replace("BBX 1 3 1 0", "BBX 100000 100000 0 0");
+    /// assert!(matches!(
+    ///     BdfFont::parse(huge.as_bytes()),
+    ///     Err(BdfParseError::GlyphTooLarge { line: 14, .. })
+    /// ));
+    /// ```
+    pub fn parse(bytes: &[u8]) -> Result<Self, BdfParseError> {
+        let text = std::str::from_utf8(bytes)?;
+        let mut font_bounding_box = None;
+        let mut ascent = None;
+        let mut descent = None;
+        let mut default_advance = None;
+        let mut default_vertical_advance = None;
+        let mut glyphs = vec![];
+
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+        while let Some((line_number, line)) = lines.next() {
+            let (keyword, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+            match keyword {
+                "FONTBOUNDINGBOX" => {
+                    font_bounding_box = Some(numbers::<4>(line_number, "FONTBOUNDINGBOX", rest)?)
+                }
+                "FONT_ASCENT" => ascent = Some(numbers::<1>(line_number, "FONT_ASCENT", rest)?[0]),
+                "FONT_DESCENT" => {
+                    descent = Some(numbers::<1>(line_number, "FONT_DESCENT", rest)?[0])
+                }
+                // a font-wide DWIDTH is allowed as of BDF 2.2
+                "DWIDTH" => default_advance = Some(numbers::<2>(line_number, "DWIDTH", rest)?[0]),
+                "DWIDTH1" => {
+                    default_vertical_advance = Some(-numbers::<2>(line_number, "DWIDTH1", rest)?[1])
+                }
+                "STARTCHAR" => {
+                    let defaults = (default_advance, default_vertical_advance);
+                    if let Some(glyph) = parse_glyph(rest.trim(), defaults, &mut lines)? {
+                        glyphs.push(glyph);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let ascent = ascent
+            .or(font_bounding_box.map(|[_, height, _, y]| height + y))
+            .ok_or(BdfParseError::MissingAscent)?;
+        let descent = descent
+            .or(font_bounding_box.map(|[_, _, _, y]| -y))
+            .unwrap_or(0);
+        Ok(BdfFont {
+            ascent,
+            descent,
+            glyphs,
+        })
+    }
+
+    /// Rasterizes all the glyphs into an atlas and builds the corresponding
+    /// [`ImageFont`].
+    #[allow(clippy::result_large_err)]
+    fn into_image_font(
+        self,
+        load_context: &mut LoadContext,
+        settings: &ImageFontLoaderSettings,
+    ) -> Result<ImageFont, ImageFontLoadError> {
+        let sizes: Vec<UVec2> = self.glyphs.iter().map(|glyph| glyph.size).collect();
+        let packing = packing::pack(&sizes).map_err(|index| ImageFontLoadError::GlyphTooLarge {
+            key: self.glyphs[index].c.to_string(),
+            size: sizes[index],
+        })?;
+
+        let mut page_data: Vec<Vec<u8>> = packing
+            .page_sizes
+            .iter()
+            .map(|size| vec![0; (size.x * size.y * 4) as usize])
+            .collect();
+        let mut index_map = HashMap::new();
+        let max_size = packing
+            .page_sizes
+            .iter()
+            .copied()
+            .fold(UVec2::ZERO, UVec2::max);
+        let mut layout = TextureAtlasLayout::new_empty(max_size.as_vec2());
+        let mut pages = vec![];
+        let mut metrics = vec![];
+        for (glyph, (page, position)) in self.glyphs.into_iter().zip(packing.positions) {
+            let page_width = packing.page_sizes[page].x;
+            for (i, _) in glyph.bitmap.iter().enumerate().filter(|(_, &set)| set) {
+                let x = position.x + i as u32 % glyph.size.x;
+                let y = position.y + i as u32 / glyph.size.x;
+                let start = ((y * page_width + x) * 4) as usize;
+                page_data[page][start..start + 4].copy_from_slice(&[255; 4]);
+            }
+            let rect = URect::from_corners(position, position + glyph.size);
+            index_map.insert(glyph.c.to_string(), layout.add_texture(rect.as_rect()));
+            pages.push(page);
+            metrics.push(GlyphMetrics {
+                // BDF offsets are from the baseline with y pointing up
+                offset: IVec2::new(
+                    glyph.offset.x,
+                    self.ascent - glyph.offset.y - glyph.size.y as i32,
+                ),
+                advance: glyph.advance,
+                vertical_advance: glyph.vertical_advance.unwrap_or(self.ascent + self.descent),
+            });
+        }
+
+        let textures = page_data
+            .into_iter()
+            .zip(packing.page_sizes)
+            .enumerate()
+            .map(|(i, (data, size))| {
+                let mut image = Image::new(
+                    Extent3d {
+                        width: size.x,
+                        height: size.y,
+                        depth_or_array_layers: 1,
+                    },
+                    TextureDimension::D2,
+                    data,
+                    TextureFormat::Rgba8UnormSrgb,
+                    RenderAssetUsages::default(),
+                );
+                settings.apply_to_texture(&mut image);
+                load_context.add_labeled_asset(page_label(i), image)
+            })
+            .collect();
+        Ok(ImageFont {
+            layout,
+            textures,
+            pages,
+            index_map,
+            max_key_len: 0,
+            metrics,
+            kerning: HashMap::new(),
+            mark_offsets: HashMap::new(),
+            line_height: (self.ascent + self.descent).max(0) as u32,
+            sampler: settings.sampler.clone(),
+            default_font_height: settings.font_height,
+            missing_glyph: settings.missing_glyph,
+        })
+    }
+}
+
+/// Parses everything after a `STARTCHAR` up to and including the `ENDCHAR`.
+/// `defaults` are the font-wide horizontal and vertical advances, if any.
+/// Returns `None` for glyphs that don't correspond to a Unicode character.
+fn parse_glyph<'a>(
+    name: &str,
+    (default_advance, default_vertical_advance): (Option<i32>, Option<i32>),
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+) -> Result<Option<BdfGlyph>, BdfParseError> {
+    let mut encoding = None;
+    let mut advance = default_advance;
+    let mut vertical_advance = default_vertical_advance;
+    let mut bounding_box: Option<(usize, [i32; 4])> = None;
+    let mut bitmap = vec![];
+    let mut in_bitmap = false;
+    for (line_number, line) in lines {
+        let line = line.trim();
+        if line == "ENDCHAR" {
+            break;
+        }
+        if in_bitmap {
+            let (_, [width, ..]) =
+                bounding_box.ok_or_else(|| BdfParseError::MissingBoundingBox {
+                    name: name.to_owned(),
+                })?;
+            let row = (0..line.len())
+                .step_by(2)
+                .map(|i| {
+                    line.get(i..i + 2)
+                        .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                })
+                .collect::<Option<Vec<u8>>>()
+                .filter(|row| row.len() * 8 >= width as usize)
+                .ok_or_else(|| BdfParseError::BadBitmapRow {
+                    line: line_number,
+                    row: line.to_owned(),
+                })?;
+            bitmap.extend((0..width as usize).map(|x| row[x / 8] & (0x80 >> (x % 8)) != 0));
+            continue;
+        }
+        let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match keyword {
+            // the second number is an optional non-Unicode encoding, which we
+            // don't care about
+            "ENCODING" => {
+                encoding = rest
+                    .split_whitespace()
+                    .next()
+                    .and_then(|n| n.parse::<i64>().ok())
+            }
+            "DWIDTH" => advance = Some(numbers::<2>(line_number, "DWIDTH", rest)?[0]),
+            "DWIDTH1" => vertical_advance = Some(-numbers::<2>(line_number, "DWIDTH1", rest)?[1]),
+            "BBX" => {
+                let bbx @ [width, height, ..] = numbers::<4>(line_number, "BBX", rest)?;
+                // check before the bitmap is allocated, since a glyph this
+                // big couldn't be packed anyway
+                let too_large = |n: i32| n > packing::MAX_PAGE_SIZE as i32;
+                if too_large(width) || too_large(height) {
+                    return Err(BdfParseError::GlyphTooLarge {
+                        line: line_number,
+                        name: name.to_owned(),
+                        width,
+                        height,
+                    });
+                }
+                bounding_box = Some((line_number, bbx));
+            }
+            "BITMAP" => in_bitmap = true,
+            _ => {}
+        }
+    }
+
+    let Some(c) = encoding
+        .and_then(|encoding| u32::try_from(encoding).ok())
+        .and_then(char::from_u32)
+    else {
+        debug!("skipping BDF glyph {name:?} with no Unicode encoding");
+        return Ok(None);
+    };
+    let (bbx_line, [width, height, x, y]) =
+        bounding_box.ok_or_else(|| BdfParseError::MissingBoundingBox {
+            name: name.to_owned(),
+        })?;
+    let size = IVec2::new(width, height).max(IVec2::ZERO).as_uvec2();
+    // tolerate glyphs with fewer bitmap rows than their bounding box claims
+    let pixels = size
+        .x
+        .checked_mul(size.y)
+        .ok_or_else(|| BdfParseError::GlyphTooLarge {
+            line: bbx_line,
+            name: name.to_owned(),
+            width,
+            height,
+        })?;
+    bitmap.resize(pixels as usize, false);
+    Ok(Some(BdfGlyph {
+        c,
+        advance: advance.unwrap_or(width),
+        vertical_advance,
+        size,
+        offset: IVec2::new(x, y),
+        bitmap,
+    }))
+}
+
+/// Parses exactly `N` whitespace-separated integers.
+fn numbers<const N: usize>(
+    line: usize,
+    keyword: &'static str,
+    rest: &str,
+) -> Result<[i32; N], BdfParseError> {
+    let error = || BdfParseError::BadValues {
+        line,
+        keyword,
+        expected: N,
+    };
+    let values = rest
+        .split_whitespace()
+        .map(|n| n.parse().map_err(|_| error()))
+        .collect::<Result<Vec<i32>, _>>()?;
+    values.try_into().map_err(|_| error())
+}
+
+/// Loader for [`ImageFont`]s stored as BDF fonts.
+#[derive(Debug, Default)]
+pub struct BdfLoader;
+
+impl AssetLoader for BdfLoader {
+    type Asset = ImageFont;
+
+    type Settings = ImageFontLoaderSettings;
+
+    type Error = ImageFontLoadError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = vec![];
+            reader.read_to_end(&mut bytes).await?;
+            let mut font = BdfFont::parse(&bytes)?.into_image_font(load_context, settings)?;
+            font.add_normalized_keys();
+            Ok(font)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["bdf"]
+    }
+}
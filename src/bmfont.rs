@@ -0,0 +1,585 @@
+//! Code for loading an [`ImageFont`] from an [AngelCode BMFont] descriptor,
+//! which is what Hiero, Littera and most other bitmap font tools export.
+//!
+//! All three flavors of the format (text, XML and binary) are supported; which
+//! one a file uses is detected from its contents, since they all conventionally
+//! use the `.fnt` extension. Multi-page fonts are supported, with each page
+//! becoming a page of the [`ImageFont`]. Fonts that pack glyphs into individual
+//! color channels are not supported.
+//!
+//! [AngelCode BMFont]: https://www.angelcode.com/products/bmfont/doc/file_format.html
+use std::str::FromStr;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::{BoxedFuture, HashMap},
+};
+use thiserror::Error;
+
+use crate::{
+    loader::{check, page_label, ImageFontLoadError, ImageFontLoaderSettings},
+    GlyphMetrics, ImageFont,
+};
+
+/// The parts of a BMFont descriptor that are relevant to rendering.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BmFont {
+    /// Distance between two lines of text.
+    pub line_height: u32,
+    /// Distance from the top of a line to the baseline.
+    pub base: u32,
+    /// Image file for each page, relative to the descriptor.
+    pub pages: Vec<String>,
+    pub chars: Vec<BmChar>,
+    pub kernings: Vec<BmKerning>,
+}
+
+/// A single `char` entry in a BMFont descriptor.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BmChar {
+    pub id: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub xoffset: i32,
+    pub yoffset: i32,
+    pub xadvance: i32,
+    pub page: u32,
+}
+
+/// A single `kerning` entry in a BMFont descriptor.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BmKerning {
+    pub first: u32,
+    pub second: u32,
+    pub amount: i32,
+}
+
+/// Errors that can show up when parsing a BMFont descriptor.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum BmFontParseError {
+    #[error("descriptor is not valid UTF-8: {0}")]
+    NotUtf8(#[from] std::str::Utf8Error),
+    #[error("unsupported binary format version {0}")]
+    UnsupportedVersion(u8),
+    #[error("binary descriptor ended unexpectedly")]
+    UnexpectedEof,
+    #[error("unterminated XML tag")]
+    MalformedXml,
+    #[error("descriptor has no `common` block")]
+    MissingCommon,
+    #[error("`{tag}` is missing the `{key}` attribute")]
+    MissingAttribute { tag: String, key: &'static str },
+    #[error("`{tag}` has invalid value {value:?} for `{key}`")]
+    InvalidAttribute {
+        tag: String,
+        key: &'static str,
+        value: String,
+    },
+    #[error("character {id} is on page {page}, which doesn't exist")]
+    UnknownPage { id: u32, page: u32 },
+}
+
+impl BmFont {
+    /// Parses a descriptor in any of the three formats.
+    ///
+    /// ```rust
+    /// # use extol_image_font::bmfont::*;
+    /// let s = r#"
+    /// info face="Example" size=8
+    /// common lineHeight=10 base=8 scaleW=64 scaleH=64 pages=1 packed=0
+    /// page id=0 file="example.png"
+    /// chars count=2
+    /// char id=65 x=0 y=0 width=5 height=8 xoffset=0 yoffset=1 xadvance=6 page=0 chnl=15
+    /// char id=86 x=5 y=0 width=5 height=8 xoffset=0 yoffset=1 xadvance=6 page=0 chnl=15
+    /// kernings count=1
+    /// kerning first=65 second=86 amount=-1
+    /// "#;
+    /// let font = BmFont::parse(s.as_bytes()).unwrap();
+    /// assert_eq!(font.line_height, 10);
+    /// assert_eq!(font.pages, vec!["example.png"]);
+    /// assert_eq!(font.chars[1].id, 'V' as u32);
+    /// assert_eq!(font.kernings[0].amount, -1);
+    /// ```
+    pub fn parse(bytes: &[u8]) -> Result<Self, BmFontParseError> {
+        let font = if let Some(binary) = bytes.strip_prefix(b"BMF") {
+            Self::parse_binary(binary)?
+        } else {
+            let text = std::str::from_utf8(bytes)?.trim_start_matches('\u{feff}');
+            if text.trim_start().starts_with('<') {
+                Self::from_records(xml_records(text)?)?
+            } else {
+                Self::from_records(text_records(text))?
+            }
+        };
+        if let Some(c) = font
+            .chars
+            .iter()
+            .find(|c| c.page as usize >= font.pages.len())
+        {
+            return Err(BmFontParseError::UnknownPage {
+                id: c.id,
+                page: c.page,
+            });
+        }
+        Ok(font)
+    }
+
+    fn from_records(records: Vec<Record>) -> Result<Self, BmFontParseError> {
+        let mut font = BmFont::default();
+        let mut saw_common = false;
+        for record in records {
+            match record.tag.as_str() {
+                "common" => {
+                    font.line_height = record.get("lineHeight")?;
+                    font.base = record.get("base")?;
+                    saw_common = true;
+                }
+                "page" => {
+                    let id: usize = record.get("id")?;
+                    if font.pages.len() <= id {
+                        font.pages.resize(id + 1, String::new());
+                    }
+                    font.pages[id] = record.get("file")?;
+                }
+                "char" => {
+                    // some tools emit a `char id=-1` entry for the "missing
+                    // glyph" glyph; we have no use for it
+                    let Ok(id) = u32::try_from(record.get::<i64>("id")?) else {
+                        continue;
+                    };
+                    font.chars.push(BmChar {
+                        id,
+                        x: record.get("x")?,
+                        y: record.get("y")?,
+                        width: record.get("width")?,
+                        height: record.get("height")?,
+                        xoffset: record.get("xoffset")?,
+                        yoffset: record.get("yoffset")?,
+                        xadvance: record.get("xadvance")?,
+                        page: record.get_or("page", 0)?,
+                    });
+                }
+                "kerning" => font.kernings.push(BmKerning {
+                    first: record.get("first")?,
+                    second: record.get("second")?,
+                    amount: record.get("amount")?,
+                }),
+                _ => {}
+            }
+        }
+        if !saw_common {
+            return Err(BmFontParseError::MissingCommon);
+        }
+        Ok(font)
+    }
+
+    fn parse_binary(data: &[u8]) -> Result<Self, BmFontParseError> {
+        let mut data = ByteReader(data);
+        let version = data.u8()?;
+        if version != 3 {
+            return Err(BmFontParseError::UnsupportedVersion(version));
+        }
+        let mut font = BmFont::default();
+        let mut saw_common = false;
+        while !data.0.is_empty() {
+            let block_type = data.u8()?;
+            let size = data.u32()? as usize;
+            let mut block = ByteReader(data.take(size)?);
+            match block_type {
+                // common
+                2 => {
+                    font.line_height = block.u16()?.into();
+                    font.base = block.u16()?.into();
+                    saw_common = true;
+                }
+                // pages: a sequence of null-terminated strings
+                3 => {
+                    for name in block.0.split(|&b| b == 0).filter(|name| !name.is_empty()) {
+                        font.pages.push(std::str::from_utf8(name)?.to_owned());
+                    }
+                }
+                // chars, 20 bytes each
+                4 => {
+                    while !block.0.is_empty() {
+                        let mut c = ByteReader(block.take(20)?);
+                        font.chars.push(BmChar {
+                            id: c.u32()?,
+                            x: c.u16()?.into(),
+                            y: c.u16()?.into(),
+                            width: c.u16()?.into(),
+                            height: c.u16()?.into(),
+                            xoffset: c.i16()?.into(),
+                            yoffset: c.i16()?.into(),
+                            xadvance: c.i16()?.into(),
+                            page: c.u8()?.into(),
+                        });
+                    }
+                }
+                // kerning pairs, 10 bytes each
+                5 => {
+                    while !block.0.is_empty() {
+                        font.kernings.push(BmKerning {
+                            first: block.u32()?,
+                            second: block.u32()?,
+                            amount: block.i16()?.into(),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !saw_common {
+            return Err(BmFontParseError::MissingCommon);
+        }
+        Ok(font)
+    }
+
+    /// Builds the [`ImageFont`], given the texture and size of each page.
+    ///
+    /// Glyphs whose rects don't fit in their page and characters that appear
+    /// twice are errors unless `settings.lenient` is set, in which case
+    /// they're logged, the glyph is dropped and the last one wins
+    /// respectively.
+    #[allow(clippy::result_large_err)]
+    fn into_image_font(
+        self,
+        textures: Vec<Handle<Image>>,
+        page_sizes: &[UVec2],
+        settings: &ImageFontLoaderSettings,
+    ) -> Result<ImageFont, ImageFontLoadError> {
+        let max_size = page_sizes.iter().copied().fold(UVec2::ZERO, UVec2::max);
+        let mut index_map = HashMap::new();
+        let mut layout = TextureAtlasLayout::new_empty(max_size.as_vec2());
+        let mut pages = vec![];
+        let mut metrics = vec![];
+        for bm_char in self.chars {
+            let Some(c) = char::from_u32(bm_char.id) else {
+                warn!("ignoring glyph for invalid codepoint {}", bm_char.id);
+                continue;
+            };
+            let key = c.to_string();
+            let min = UVec2::new(bm_char.x, bm_char.y);
+            let size = UVec2::new(bm_char.width, bm_char.height);
+            let Some(max) = min
+                .x
+                .checked_add(size.x)
+                .zip(min.y.checked_add(size.y))
+                .map(|(x, y)| UVec2::new(x, y))
+            else {
+                check(
+                    settings.lenient,
+                    ImageFontLoadError::RectOverflow {
+                        key,
+                        top_left: min,
+                        size,
+                    },
+                )?;
+                continue;
+            };
+            let rect = URect::from_corners(min, max);
+            let page = bm_char.page as usize;
+            let page_size = page_sizes.get(page).copied().unwrap_or(UVec2::ZERO);
+            if rect.max.cmpgt(page_size).any() {
+                check(
+                    settings.lenient,
+                    ImageFontLoadError::RectOutOfBounds {
+                        key,
+                        rect,
+                        size: page_size,
+                    },
+                )?;
+                continue;
+            }
+            if index_map.contains_key(&key) {
+                check(
+                    settings.lenient,
+                    ImageFontLoadError::DuplicateEntry { key: key.clone() },
+                )?;
+            }
+            index_map.insert(key, layout.add_texture(rect.as_rect()));
+            pages.push(page);
+            metrics.push(GlyphMetrics {
+                offset: IVec2::new(bm_char.xoffset, bm_char.yoffset),
+                advance: bm_char.xadvance,
+                // BMFont has no vertical metrics, so stack glyphs like lines
+                vertical_advance: self.line_height as i32,
+            });
+        }
+        let kerning = self
+            .kernings
+            .into_iter()
+            .filter_map(|kerning| {
+                let first = char::from_u32(kerning.first)?;
+                let second = char::from_u32(kerning.second)?;
+                Some(((first, second), kerning.amount))
+            })
+            .collect();
+        Ok(ImageFont {
+            layout,
+            textures,
+            pages,
+            index_map,
+            max_key_len: 0,
+            metrics,
+            kerning,
+            mark_offsets: HashMap::new(),
+            line_height: self.line_height,
+            sampler: settings.sampler.clone(),
+            default_font_height: settings.font_height,
+            missing_glyph: settings.missing_glyph,
+        })
+    }
+}
+
+/// A tag and its attributes; this is the common structure of the text and XML
+/// formats.
+struct Record {
+    tag: String,
+    attributes: HashMap<String, String>,
+}
+
+impl Record {
+    fn get<T: FromStr>(&self, key: &'static str) -> Result<T, BmFontParseError> {
+        let value = self
+            .attributes
+            .get(key)
+            .ok_or_else(|| BmFontParseError::MissingAttribute {
+                tag: self.tag.clone(),
+                key,
+            })?;
+        value
+            .parse()
+            .map_err(|_| BmFontParseError::InvalidAttribute {
+                tag: self.tag.clone(),
+                key,
+                value: value.clone(),
+            })
+    }
+
+    fn get_or<T: FromStr>(&self, key: &'static str, default: T) -> Result<T, BmFontParseError> {
+        if self.attributes.contains_key(key) {
+            self.get(key)
+        } else {
+            Ok(default)
+        }
+    }
+}
+
+/// Splits the text format into records. Each line is a tag followed by
+/// `key=value` pairs, where values may be quoted.
+fn text_records(text: &str) -> Vec<Record> {
+    text.lines()
+        .filter_map(|line| {
+            let mut tokens = split_unquoted_whitespace(line).into_iter();
+            let tag = tokens.next()?.to_owned();
+            let attributes = tokens
+                .filter_map(|token| token.split_once('='))
+                .map(|(key, value)| (key.to_owned(), value.trim_matches('"').to_owned()))
+                .collect();
+            Some(Record { tag, attributes })
+        })
+        .collect()
+}
+
+fn split_unquoted_whitespace(line: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    let mut start = None;
+    let mut in_quotes = false;
+    for (i, c) in line.char_indices() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        }
+        if c.is_whitespace() && !in_quotes {
+            if let Some(start) = start.take() {
+                tokens.push(&line[start..i]);
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    if let Some(start) = start {
+        tokens.push(&line[start..]);
+    }
+    tokens
+}
+
+/// Splits the XML format into records. This is nowhere near a general XML
+/// parser; it only understands elements with attributes, since that's all a
+/// BMFont descriptor contains.
+fn xml_records(mut text: &str) -> Result<Vec<Record>, BmFontParseError> {
+    let mut records = vec![];
+    while let Some(start) = text.find('<') {
+        text = &text[start + 1..];
+        if let Some(comment) = text.strip_prefix("!--") {
+            let end = comment.find("-->").ok_or(BmFontParseError::MalformedXml)?;
+            text = &comment[end + 3..];
+            continue;
+        }
+        let end = find_unquoted(text, '>').ok_or(BmFontParseError::MalformedXml)?;
+        let element = &text[..end];
+        text = &text[end + 1..];
+        if element.starts_with(['?', '!', '/']) {
+            continue;
+        }
+        let element = element.trim_end_matches('/');
+        let (tag, mut rest) = element
+            .split_once(char::is_whitespace)
+            .unwrap_or((element, ""));
+        let mut attributes = HashMap::new();
+        while let Some((key, value)) = rest.split_once('=') {
+            let value = value.trim_start();
+            let quote = value
+                .chars()
+                .next()
+                .filter(|&c| c == '"' || c == '\'')
+                .ok_or(BmFontParseError::MalformedXml)?;
+            let value = &value[1..];
+            let close = value.find(quote).ok_or(BmFontParseError::MalformedXml)?;
+            attributes.insert(key.trim().to_owned(), unescape_xml(&value[..close]));
+            rest = &value[close + 1..];
+        }
+        records.push(Record {
+            tag: tag.to_owned(),
+            attributes,
+        });
+    }
+    Ok(records)
+}
+
+fn find_unquoted(text: &str, needle: char) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == needle => return Some(i),
+            None => {}
+        }
+    }
+    None
+}
+
+fn unescape_xml(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let unescaped = match entity {
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match unescaped {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Little-endian reader for the binary format.
+struct ByteReader<'a>(&'a [u8]);
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], BmFontParseError> {
+        if self.0.len() < n {
+            return Err(BmFontParseError::UnexpectedEof);
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, BmFontParseError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, BmFontParseError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn i16(&mut self) -> Result<i16, BmFontParseError> {
+        Ok(i16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, BmFontParseError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+/// Loader for [`ImageFont`]s stored as BMFont descriptors.
+#[derive(Debug, Default)]
+pub struct BmFontLoader;
+
+impl AssetLoader for BmFontLoader {
+    type Asset = ImageFont;
+
+    type Settings = ImageFontLoaderSettings;
+
+    type Error = ImageFontLoadError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = vec![];
+            reader.read_to_end(&mut bytes).await?;
+            let bm_font = BmFont::parse(&bytes)?;
+
+            let parent = load_context
+                .path()
+                .parent()
+                .expect("asset's parent is None?")
+                .to_owned();
+            let mut textures = vec![];
+            let mut page_sizes = vec![];
+            for (i, file) in bm_font.pages.iter().enumerate() {
+                let image_path = parent.join(file);
+                let mut image = load_context
+                    .load_direct(image_path.clone())
+                    .await?
+                    .take::<Image>()
+                    .ok_or(ImageFontLoadError::NotAnImage(image_path))?;
+                settings.apply_to_texture(&mut image);
+                page_sizes.push(image.size());
+                textures.push(load_context.add_labeled_asset(page_label(i), image));
+            }
+
+            let mut font = bm_font.into_image_font(textures, &page_sizes, settings)?;
+            font.add_normalized_keys();
+            Ok(font)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["fnt"]
+    }
+}
@@ -0,0 +1,80 @@
+//! Character tables for legacy 8-bit code pages, for loading fonts laid out in
+//! code page order (such as the 16x16 sheets used by most roguelikes).
+use serde::{Deserialize, Serialize};
+
+/// An 8-bit code page. Use it with [`ImageFontLayout::CodePage`] or
+/// [`GridCodepoints::CodePage`].
+///
+/// The control codes at the start of CP437 and CP850 map to the symbols that
+/// IBM PCs displayed for them (`☺`, `☻`, `♥` and so on), since that's what
+/// font sheets draw there. This means CP850 has `¶` and `§` twice, once there
+/// and once in the upper half.
+///
+/// ```rust
+/// # use extol_image_font::codepage::CodePage;
+/// assert_eq!(CodePage::Cp437.to_char(0x01), '☺');
+/// assert_eq!(CodePage::Cp437.to_char(0xC9), '╔');
+/// assert_eq!(CodePage::Cp850.to_char(0x9B), 'ø');
+/// assert_eq!(CodePage::Iso8859_1.to_char(0xE9), 'é');
+/// ```
+///
+/// [`ImageFontLayout::CodePage`]: crate::loader::ImageFontLayout::CodePage
+/// [`GridCodepoints::CodePage`]: crate::loader::GridCodepoints::CodePage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CodePage {
+    /// The original IBM PC character set, with box drawing characters and
+    /// some Greek letters in the upper half.
+    Cp437,
+    /// The Western European variant of CP437, which trades some of the box
+    /// drawing characters for accented letters.
+    Cp850,
+    /// Latin-1, whose 256 characters are exactly the first 256 Unicode
+    /// codepoints.
+    Iso8859_1,
+}
+
+/// What CP437 and CP850 display for bytes 0x00 to 0x1F. NUL is blank.
+const DOS_LOW: [char; 32] = [
+    '\0', '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼', //
+    '►', '◄', '↕', '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼',
+];
+
+const CP437_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', //
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', //
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', //
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐', //
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧', //
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀', //
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', //
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{A0}',
+];
+
+const CP850_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', //
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', 'ø', '£', 'Ø', '×', 'ƒ', //
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '®', '¬', '½', '¼', '¡', '«', '»', //
+    '░', '▒', '▓', '│', '┤', 'Á', 'Â', 'À', '©', '╣', '║', '╗', '╝', '¢', '¥', '┐', //
+    '└', '┴', '┬', '├', '─', '┼', 'ã', 'Ã', '╚', '╔', '╩', '╦', '╠', '═', '╬', '¤', //
+    'ð', 'Ð', 'Ê', 'Ë', 'È', 'ı', 'Í', 'Î', 'Ï', '┘', '┌', '█', '▄', '¦', 'Ì', '▀', //
+    'Ó', 'ß', 'Ô', 'Ò', 'õ', 'Õ', 'µ', 'þ', 'Þ', 'Ú', 'Û', 'Ù', 'ý', 'Ý', '¯', '´', //
+    '\u{AD}', '±', '‗', '¾', '¶', '§', '÷', '¸', '°', '¨', '·', '¹', '³', '²', '■', '\u{A0}',
+];
+
+impl CodePage {
+    /// The character that `byte` stands for in this code page.
+    pub fn to_char(self, byte: u8) -> char {
+        match (self, byte) {
+            (CodePage::Cp437 | CodePage::Cp850, 0x00..=0x1F) => DOS_LOW[byte as usize],
+            (CodePage::Cp437 | CodePage::Cp850, 0x7F) => '⌂',
+            (CodePage::Cp437, 0x80..=0xFF) => CP437_HIGH[byte as usize - 0x80],
+            (CodePage::Cp850, 0x80..=0xFF) => CP850_HIGH[byte as usize - 0x80],
+            _ => char::from(byte),
+        }
+    }
+
+    /// All 256 characters of the code page, in order.
+    pub fn chars(self) -> impl Iterator<Item = char> {
+        (0..=u8::MAX).map(move |byte| self.to_char(byte))
+    }
+}
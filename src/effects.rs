@@ -0,0 +1,267 @@
+//! Animated per-glyph effects, like wavy or shaking text.
+//!
+//! Effects are deterministic: the same [`TextEffects`] (including its
+//! [`time`](TextEffects::time) and [`seed`](TextEffects::seed)) always
+//! renders the same image, so you can snapshot test them by setting the time
+//! yourself.
+//!
+//! ```rust
+//! # use bevy::{
+//! #     prelude::*,
+//! #     render::{
+//! #         render_asset::RenderAssetUsages,
+//! #         render_resource::{Extent3d, TextureDimension, TextureFormat},
+//! #     },
+//! #     utils::HashMap,
+//! # };
+//! # use extol_image_font::{
+//! #     effects::{TextEffect, TextEffects},
+//! #     render_text, GlyphMetrics, ImageFont, ImageFontText,
+//! # };
+//! # let mut images = Assets::<Image>::default();
+//! # let texture = images.add(Image::new_fill(
+//! #     Extent3d { width: 12, height: 4, depth_or_array_layers: 1 },
+//! #     TextureDimension::D2,
+//! #     &[255; 4],
+//! #     TextureFormat::Rgba8UnormSrgb,
+//! #     RenderAssetUsages::default(),
+//! # ));
+//! # let mut layout = TextureAtlasLayout::new_empty(Vec2::new(12.0, 4.0));
+//! # let mut index_map = HashMap::new();
+//! # let mut metrics = vec![];
+//! # for (i, key) in ["A", "B", "C"].into_iter().enumerate() {
+//! #     let min = Vec2::new(i as f32 * 4.0, 0.0);
+//! #     index_map.insert(key.to_owned(), layout.add_texture(Rect::from_corners(min, min + 4.0)));
+//! #     metrics.push(GlyphMetrics { offset: IVec2::ZERO, advance: 4, vertical_advance: 4 });
+//! # }
+//! # let mut fonts = Assets::<ImageFont>::default();
+//! # let font = fonts.add(ImageFont::new(layout, vec![texture], vec![0; 3], index_map, metrics));
+//! let shake = |seed, time| {
+//!     let effects = TextEffects {
+//!         seed,
+//!         time,
+//!         ..default()
+//!     }
+//!     .with(TextEffect::Shake { amplitude: 2.0, speed: 10.0 }, None);
+//!     let text = ImageFontText::default()
+//!         .text("ABC")
+//!         .font(font.clone())
+//!         .effects(effects);
+//!     render_text(&text, &fonts, &images).unwrap().data
+//! };
+//! assert_eq!(shake(7, 1.25), shake(7, 1.25));
+//! ```
+use std::{f32::consts::TAU, ops::Range};
+
+use bevy::prelude::*;
+
+use crate::{layout::ImageFontTextLayout, ImageFontText};
+
+/// The effects applied to an [`ImageFontText`].
+///
+/// ```rust
+/// # use extol_image_font::{ImageFontText, effects::{TextEffect, TextEffects}};
+/// let text = ImageFontText::default()
+///     .text("Watch out for the GHOST")
+///     .effects(TextEffects::default().with(
+///         TextEffect::Shake {
+///             amplitude: 1.0,
+///             speed: 12.0,
+///         },
+///         Some(18..23),
+///     ));
+/// ```
+#[derive(Debug, Clone, Default, Reflect)]
+pub struct TextEffects {
+    /// Each effect, along with the range of grapheme clusters it applies to,
+    /// or `None` to apply it to the whole text.
+    pub effects: Vec<(TextEffect, Option<Range<usize>>)>,
+    /// Seed for the random parts of effects, such as how text shakes.
+    pub seed: u64,
+    /// Time since the effects started, in seconds. This is advanced by
+    /// [`animate_text_effects`], which only rerenders the text when that
+    /// changes how it looks.
+    pub time: f32,
+}
+
+/// An effect that can be applied to some or all of the glyphs of an
+/// [`ImageFontText`].
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum TextEffect {
+    /// Moves glyphs up and down along a sine wave.
+    Wave {
+        /// How far glyphs move up and down, in unscaled pixels.
+        amplitude: f32,
+        /// How long the wave is, in glyphs.
+        wavelength: f32,
+        /// How many waves pass by each second.
+        speed: f32,
+    },
+    /// Moves glyphs around randomly.
+    Shake {
+        /// How far glyphs move in each direction, in unscaled pixels.
+        amplitude: f32,
+        /// How many times per second glyphs move.
+        speed: f32,
+    },
+    /// Tints glyphs with colors going around the color wheel. This works best
+    /// with white fonts.
+    Rainbow {
+        /// How many times per second the colors go around the wheel.
+        speed: f32,
+        /// How far apart the colors of neighboring glyphs are, as a fraction
+        /// of the wheel.
+        spread: f32,
+    },
+    /// Fades glyphs in one after the other.
+    FadeIn {
+        /// Time between one glyph starting to fade in and the next, in
+        /// seconds.
+        delay: f32,
+        /// How long it takes each glyph to fade in, in seconds.
+        duration: f32,
+    },
+}
+
+/// What the effects do to a single glyph.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct GlyphEffect {
+    /// How far to move the glyph, in unscaled pixels.
+    pub offset: IVec2,
+    /// What to multiply the glyph's color by.
+    pub tint: [f32; 4],
+}
+
+impl TextEffects {
+    /// Adds an effect, applied to the given range of grapheme clusters, or to
+    /// the whole text if `span` is `None`.
+    pub fn with(mut self, effect: TextEffect, span: Option<Range<usize>>) -> Self {
+        self.effects.push((effect, span));
+        self
+    }
+
+    /// How far effects can move glyphs in any direction, so that the image
+    /// can be made big enough for them without changing size from one frame
+    /// to the next.
+    pub(crate) fn padding(&self) -> IVec2 {
+        self.effects
+            .iter()
+            .map(|(effect, _)| match *effect {
+                TextEffect::Wave { amplitude, .. } => IVec2::new(0, amplitude.abs().ceil() as i32),
+                TextEffect::Shake { amplitude, .. } => IVec2::splat(amplitude.abs().ceil() as i32),
+                TextEffect::Rainbow { .. } | TextEffect::FadeIn { .. } => IVec2::ZERO,
+            })
+            .fold(IVec2::ZERO, IVec2::max)
+    }
+
+    /// Whether moving the time from `from` to `to` can change how the text
+    /// looks, given how many grapheme clusters it has (if known). This errs
+    /// on the side of saying yes.
+    fn changes_between(&self, from: f32, to: f32, clusters: Option<usize>) -> bool {
+        self.effects.iter().any(|(effect, _)| match *effect {
+            TextEffect::Wave {
+                amplitude, speed, ..
+            } => amplitude != 0.0 && speed != 0.0,
+            TextEffect::Shake { amplitude, speed } => {
+                amplitude != 0.0 && (from * speed).floor() != (to * speed).floor()
+            }
+            TextEffect::Rainbow { speed, .. } => speed != 0.0,
+            // the last glyph is done fading in by then
+            TextEffect::FadeIn { delay, duration } => match clusters {
+                Some(clusters) => from < clusters.saturating_sub(1) as f32 * delay + duration,
+                None => true,
+            },
+        })
+    }
+
+    /// What the effects do to the glyph for the given grapheme cluster.
+    pub(crate) fn glyph_effect(&self, cluster: usize) -> GlyphEffect {
+        let mut glyph_effect = GlyphEffect {
+            offset: IVec2::ZERO,
+            tint: [1.0; 4],
+        };
+        let position = cluster as f32;
+        for (effect, span) in &self.effects {
+            if span.as_ref().is_some_and(|span| !span.contains(&cluster)) {
+                continue;
+            }
+            match *effect {
+                TextEffect::Wave {
+                    amplitude,
+                    wavelength,
+                    speed,
+                } => {
+                    let phase = self.time * speed - position / wavelength;
+                    glyph_effect.offset.y += (amplitude * (phase * TAU).sin()).round() as i32;
+                }
+                TextEffect::Shake { amplitude, speed } => {
+                    let step = (self.time * speed).floor() as u64;
+                    let random = |axis: u64| {
+                        let bits = hash(self.seed ^ hash(cluster as u64 ^ hash(step ^ axis)));
+                        // uniform in [-1, 1]
+                        (bits >> 40) as f32 / (1 << 23) as f32 - 1.0
+                    };
+                    glyph_effect.offset += (Vec2::new(random(0), random(1)) * amplitude)
+                        .round()
+                        .as_ivec2();
+                }
+                TextEffect::Rainbow { speed, spread } => {
+                    let hue = (self.time * speed + position * spread).rem_euclid(1.0);
+                    let [r, g, b, _] = Color::hsl(hue * 360.0, 1.0, 0.5).as_rgba_f32();
+                    for (tint, color) in glyph_effect.tint.iter_mut().zip([r, g, b]) {
+                        *tint *= color;
+                    }
+                }
+                TextEffect::FadeIn { delay, duration } => {
+                    let start = position * delay;
+                    let alpha = if duration > 0.0 {
+                        ((self.time - start) / duration).clamp(0.0, 1.0)
+                    } else {
+                        f32::from(u8::from(self.time >= start))
+                    };
+                    glyph_effect.tint[3] *= alpha;
+                }
+            }
+        }
+        glyph_effect
+    }
+}
+
+/// The SplitMix64 finalizer, which is all the randomness effects need.
+fn hash(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// System that advances the [`TextEffects::time`] of every [`ImageFontText`]
+/// that has effects, rerendering it whenever that changes how it looks.
+pub fn animate_text_effects(
+    time: Res<Time>,
+    mut query: Query<(&mut ImageFontText, Option<&ImageFontTextLayout>)>,
+) {
+    for (mut image_font_text, layout) in &mut query {
+        let effects = &image_font_text.effects;
+        if effects.effects.is_empty() {
+            continue;
+        }
+        let from = effects.time;
+        let to = from + time.delta_seconds();
+        // the layout is missing until the text has been rendered once
+        let clusters = layout.map(|layout| {
+            layout
+                .glyphs
+                .iter()
+                .map(|glyph| glyph.cluster + 1)
+                .max()
+                .unwrap_or(0)
+        });
+        let changes = effects.changes_between(from, to, clusters);
+        // keep the time up to date without rerendering the text
+        image_font_text.bypass_change_detection().effects.time = to;
+        if changes {
+            image_font_text.set_changed();
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! Resizing rendered text with a [`ScaleFilter`].
+use bevy::math::UVec2;
+use image::{
+    imageops::{self, FilterType},
+    Rgba, RgbaImage,
+};
+
+use crate::ScaleFilter;
+
+/// Resizes the image to `size` using `filter`.
+pub(crate) fn resize(image: &RgbaImage, size: UVec2, filter: ScaleFilter) -> RgbaImage {
+    if (image.width(), image.height()) == (size.x, size.y) {
+        return image.clone();
+    }
+    match filter {
+        ScaleFilter::Nearest => imageops::resize(image, size.x, size.y, FilterType::Nearest),
+        ScaleFilter::Triangle => smooth(image, size, FilterType::Triangle),
+        ScaleFilter::Lanczos => smooth(image, size, FilterType::Lanczos3),
+        ScaleFilter::Area => {
+            let mut image = image.clone();
+            premultiply(&mut image);
+            let mut image = area(&image, size);
+            unpremultiply(&mut image);
+            image
+        }
+        ScaleFilter::Scale2x => upscale(image, size, 2, scale2x),
+        ScaleFilter::Scale3x => upscale(image, size, 3, scale3x),
+    }
+}
+
+/// Resizes with one of `image`'s filters, which blend neighboring pixels.
+fn smooth(image: &RgbaImage, size: UVec2, filter: FilterType) -> RgbaImage {
+    // blend with premultiplied alpha, or the transparent black around glyphs
+    // leaves dark fringes
+    let mut image = image.clone();
+    premultiply(&mut image);
+    let mut image = imageops::resize(&image, size.x, size.y, filter);
+    unpremultiply(&mut image);
+    image
+}
+
+/// Applies a pixel art upscaler that multiplies the size by `factor` for as
+/// long as that doesn't overshoot `size`, then makes up the difference with
+/// nearest neighbor scaling.
+fn upscale(
+    image: &RgbaImage,
+    size: UVec2,
+    factor: u32,
+    upscaler: fn(&RgbaImage) -> RgbaImage,
+) -> RgbaImage {
+    let mut image = image.clone();
+    while image.width() * factor <= size.x && image.height() * factor <= size.y {
+        image = upscaler(&image);
+    }
+    if (image.width(), image.height()) == (size.x, size.y) {
+        return image;
+    }
+    imageops::resize(&image, size.x, size.y, FilterType::Nearest)
+}
+
+fn premultiply(image: &mut RgbaImage) {
+    for Rgba([r, g, b, a]) in image.pixels_mut() {
+        for channel in [r, g, b] {
+            *channel = (*channel as u32 * *a as u32 / 255) as u8;
+        }
+    }
+}
+
+fn unpremultiply(image: &mut RgbaImage) {
+    for Rgba([r, g, b, a]) in image.pixels_mut() {
+        if *a == 0 {
+            continue;
+        }
+        for channel in [r, g, b] {
+            *channel = (*channel as u32 * 255 / *a as u32).min(255) as u8;
+        }
+    }
+}
+
+/// Which source pixels each output pixel covers along one axis, and how much
+/// of each.
+fn coverage(from: u32, to: u32) -> Vec<Vec<(u32, f32)>> {
+    let step = from as f32 / to as f32;
+    (0..to)
+        .map(|i| {
+            let (start, end) = (i as f32 * step, (i + 1) as f32 * step);
+            (start.floor() as u32..(end.ceil() as u32).min(from))
+                .map(|source| {
+                    let overlap = end.min(source as f32 + 1.0) - start.max(source as f32);
+                    (source, overlap / step)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Resizes by averaging all of the source pixels each output pixel covers.
+fn area(image: &RgbaImage, size: UVec2) -> RgbaImage {
+    let columns = coverage(image.width(), size.x);
+    let rows = coverage(image.height(), size.y);
+    RgbaImage::from_fn(size.x, size.y, |x, y| {
+        let mut sum = [0.0; 4];
+        for &(source_y, weight_y) in &rows[y as usize] {
+            for &(source_x, weight_x) in &columns[x as usize] {
+                let pixel = image.get_pixel(source_x, source_y);
+                for (sum, channel) in sum.iter_mut().zip(pixel.0) {
+                    *sum += channel as f32 * weight_x * weight_y;
+                }
+            }
+        }
+        Rgba(sum.map(|channel| channel.round().clamp(0.0, 255.0) as u8))
+    })
+}
+
+/// The pixel at `(x + dx, y + dy)`, or the nearest one inside the image.
+fn neighbor(image: &RgbaImage, x: u32, y: u32, dx: i32, dy: i32) -> Rgba<u8> {
+    let x = x.saturating_add_signed(dx).min(image.width() - 1);
+    let y = y.saturating_add_signed(dy).min(image.height() - 1);
+    *image.get_pixel(x, y)
+}
+
+/// The Scale2x (also known as EPX) pixel art upscaler, which doubles the size
+/// of the image while smoothing diagonal edges.
+fn scale2x(image: &RgbaImage) -> RgbaImage {
+    let mut output = RgbaImage::new(image.width() * 2, image.height() * 2);
+    for (x, y, &p) in image.enumerate_pixels() {
+        let n = |dx, dy| neighbor(image, x, y, dx, dy);
+        let (a, b, c, d) = (n(0, -1), n(1, 0), n(-1, 0), n(0, 1));
+        let corners = [
+            if c == a && c != d && a != b { a } else { p },
+            if a == b && a != c && b != d { b } else { p },
+            if d == c && d != b && c != a { c } else { p },
+            if b == d && b != a && d != c { d } else { p },
+        ];
+        for (i, pixel) in corners.into_iter().enumerate() {
+            output.put_pixel(x * 2 + i as u32 % 2, y * 2 + i as u32 / 2, pixel);
+        }
+    }
+    output
+}
+
+/// The Scale3x pixel art upscaler, which triples the size of the image while
+/// smoothing diagonal edges.
+fn scale3x(image: &RgbaImage) -> RgbaImage {
+    let mut output = RgbaImage::new(image.width() * 3, image.height() * 3);
+    for (x, y, &e) in image.enumerate_pixels() {
+        let n = |dx, dy| neighbor(image, x, y, dx, dy);
+        let (a, b, c) = (n(-1, -1), n(0, -1), n(1, -1));
+        let (d, f) = (n(-1, 0), n(1, 0));
+        let (g, h, i) = (n(-1, 1), n(0, 1), n(1, 1));
+        let pixels = [
+            if d == b && b != f && d != h { d } else { e },
+            if (d == b && b != f && d != h && e != c) || (b == f && b != d && f != h && e != a) {
+                b
+            } else {
+                e
+            },
+            if b == f && b != d && f != h { f } else { e },
+            if (d == b && b != f && d != h && e != g) || (d == h && d != f && h != b && e != a) {
+                d
+            } else {
+                e
+            },
+            e,
+            if (b == f && b != d && f != h && e != i) || (h == f && d != f && h != b && e != c) {
+                f
+            } else {
+                e
+            },
+            if d == h && d != f && h != b { d } else { e },
+            if (d == h && d != f && h != b && e != i) || (h == f && d != f && h != b && e != g) {
+                h
+            } else {
+                e
+            },
+            if h == f && d != f && h != b { f } else { e },
+        ];
+        for (index, pixel) in pixels.into_iter().enumerate() {
+            output.put_pixel(x * 3 + index as u32 % 3, y * 3 + index as u32 / 3, pixel);
+        }
+    }
+    output
+}
@@ -0,0 +1,695 @@
+//! Works out where each glyph of an [`ImageFontText`] goes.
+use std::ops::Range;
+
+use bevy::prelude::*;
+use unicode_bidi::ParagraphBidiInfo;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    scaled_size, GlyphMetrics, GlyphRef, ImageFont, ImageFontRenderError, ImageFontText, TabStops,
+    TextDirection, TextFit, TextOverflow, WritingMode,
+};
+
+/// Where each glyph of an [`ImageFontText`] was drawn. This is added to
+/// entities whenever their text is rendered, for things like pointing at a
+/// word or spawning particles from letters.
+#[derive(Debug, Clone, Default, Reflect, Component)]
+pub struct ImageFontTextLayout {
+    /// Every glyph of the text, in the order it was drawn in.
+    pub glyphs: Vec<GlyphLayout>,
+    /// Size of the rendered image, in pixels.
+    pub size: UVec2,
+}
+
+/// Where a single glyph of an [`ImageFontText`] was drawn.
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct GlyphLayout {
+    /// The key the glyph was found under in [`ImageFont::index_map`]. This
+    /// is usually a single character, but can also be a grapheme cluster or
+    /// a ligature.
+    pub key: String,
+    /// Index of the grapheme cluster in the (normalized) text that the glyph
+    /// comes from. Ligatures count as coming from their first cluster.
+    pub cluster: usize,
+    /// Index of the glyph in [`ImageFont::layout`].
+    pub index: usize,
+    /// The page the glyph is on; see [`ImageFont::pages`].
+    pub page: usize,
+    /// Where the glyph is in the page's texture, in pixels.
+    pub source: Rect,
+    /// Where the glyph was drawn in the rendered image, in pixels, with the
+    /// origin at the top left. This includes scaling and [effects].
+    ///
+    /// [effects]: crate::effects
+    pub destination: Rect,
+    /// The line the glyph is on, or the column for vertical text.
+    pub line: usize,
+    /// Whether the glyph was drawn, or hidden by
+    /// [`ImageFontText::visible_chars`].
+    pub visible: bool,
+}
+
+/// A glyph along with where to draw it.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PlacedGlyph<'a> {
+    /// The key the glyph was found under in [`ImageFont::index_map`].
+    pub key: &'a str,
+    /// Index of the glyph in [`ImageFont::layout`].
+    pub index: usize,
+    /// Where the top-left corner of the glyph goes, in unscaled pixels
+    /// relative to the start of the first line. Can be negative if a glyph
+    /// sticks out past the start of the text.
+    pub position: IVec2,
+    /// Index of the grapheme cluster in the (normalized) text that the glyph
+    /// comes from. Ligatures count as coming from their first cluster.
+    pub cluster: usize,
+    /// The line the glyph is on, or the column for vertical text.
+    pub line: usize,
+}
+
+/// The result of [`layout_text`].
+#[derive(Debug, Clone)]
+pub(crate) struct TextLayout<'a> {
+    pub glyphs: Vec<PlacedGlyph<'a>>,
+    /// The size of the area the pen moved over. Glyphs can stick out of it
+    /// because of their offsets.
+    pub size: IVec2,
+}
+
+/// Normalizes the text, splits it into lines and positions each of its
+/// glyphs.
+///
+/// Horizontal lines are [`ImageFont::line_height`] apart. Vertical columns
+/// are as wide as the widest glyph in the font, with each glyph centered in
+/// its column. Tabs split lines into pieces that are laid out separately,
+/// with each one starting at a tab stop. Lines longer than
+/// [`ImageFontText::max_width`] are handled according to
+/// [`ImageFontText::overflow`].
+#[allow(clippy::result_large_err)]
+pub(crate) fn layout_text<'a>(
+    font: &'a ImageFont,
+    image_font_text: &ImageFontText,
+) -> Result<TextLayout<'a>, ImageFontRenderError> {
+    let text = image_font_text.normalization.apply(&image_font_text.text);
+    let clusters: Vec<usize> = text.grapheme_indices(true).map(|(i, _)| i).collect();
+    let writing_mode = image_font_text.writing_mode;
+    let vertical = writing_mode != WritingMode::Horizontal;
+    let layouter = LineLayouter {
+        font,
+        clusters: &clusters,
+        vertical,
+        tab_width: tab_width(font, image_font_text.tab_stops, vertical),
+        column_width: if vertical {
+            font.metrics
+                .iter()
+                .map(|metrics| metrics.advance)
+                .max()
+                .unwrap_or(0)
+        } else {
+            0
+        },
+    };
+    // the limit is given in rendered pixels, and effects need room too
+    let limit = image_font_text.max_width.map(|max_width| {
+        let scale = image_font_text.render_scale(font).unwrap_or(1.0);
+        let padding = image_font_text.effects.padding();
+        let padding = if vertical { padding.y } else { padding.x };
+        ((max_width as f32 / scale).floor() as i32 - 2 * padding).max(0)
+    });
+
+    let mut lines = vec![];
+    for (start, paragraph) in split_paragraphs(&text) {
+        // work out the direction of the whole paragraph up front, since
+        // otherwise each piece would pick its own
+        let direction = match image_font_text.direction {
+            TextDirection::Auto
+                if ParagraphBidiInfo::new(paragraph, None)
+                    .paragraph_level
+                    .is_rtl() =>
+            {
+                TextDirection::Rtl
+            }
+            TextDirection::Auto => TextDirection::Ltr,
+            direction => direction,
+        };
+        let range = start..start + paragraph.len();
+        match limit {
+            Some(limit) => layouter.fit(
+                &text,
+                range,
+                direction,
+                limit,
+                image_font_text.overflow,
+                &mut lines,
+            )?,
+            None => lines.push((pieces(&text, range), direction)),
+        }
+    }
+
+    let mut layout = TextLayout {
+        glyphs: vec![],
+        size: IVec2::ZERO,
+    };
+    let count = lines.len();
+    for (line, (pieces, direction)) in lines.into_iter().enumerate() {
+        if vertical {
+            let column = match writing_mode {
+                WritingMode::VerticalRl => count - 1 - line,
+                _ => line,
+            };
+            let x = column as i32 * layouter.column_width;
+            let pen = layouter.place(&pieces, direction, line, x, &mut layout.glyphs)?;
+            layout.size.y = layout.size.y.max(pen);
+        } else {
+            let y = line as i32 * font.line_height as i32;
+            let pen = layouter.place(&pieces, direction, line, y, &mut layout.glyphs)?;
+            layout.size = IVec2::new(layout.size.x.max(pen), y + font.line_height as i32);
+        }
+    }
+    if vertical {
+        layout.size.x = count as i32 * layouter.column_width;
+    }
+
+    Ok(layout)
+}
+
+/// A piece of a line that's laid out in one go.
+#[derive(Debug, Clone, Copy)]
+struct Piece<'t> {
+    /// Byte offset of the piece in the text.
+    start: usize,
+    text: &'t str,
+    /// Whether the piece starts at a tab stop, rather than where the piece
+    /// before it left off.
+    after_tab: bool,
+    /// Whether the piece is an ellipsis standing in for the text at `start`,
+    /// rather than part of the text itself.
+    ellipsis: bool,
+}
+
+/// The pieces between the tabs in part of the text.
+fn pieces(text: &str, range: Range<usize>) -> Vec<Piece<'_>> {
+    let mut start = range.start;
+    text[range]
+        .split('\t')
+        .enumerate()
+        .map(|(i, piece)| {
+            let piece = Piece {
+                start,
+                text: piece,
+                after_tab: i > 0,
+                ellipsis: false,
+            };
+            start += piece.text.len() + 1;
+            piece
+        })
+        .collect()
+}
+
+/// An ellipsis standing in for the text at `start`.
+fn ellipsis(font: &ImageFont, start: usize) -> Piece<'static> {
+    Piece {
+        start,
+        text: if font.index_map.contains_key("…") {
+            "…"
+        } else {
+            "..."
+        },
+        after_tab: false,
+        ellipsis: true,
+    }
+}
+
+/// Splits the text at each newline, returning each paragraph along with the
+/// byte offset it starts at.
+fn split_paragraphs(text: &str) -> Vec<(usize, &str)> {
+    let mut start = 0;
+    text.split('\n')
+        .map(|paragraph| {
+            let piece = (start, paragraph.strip_suffix('\r').unwrap_or(paragraph));
+            start += paragraph.len() + 1;
+            piece
+        })
+        .collect()
+}
+
+/// Where the text in `range` ends if whitespace at the end is left out.
+fn trim_end(text: &str, range: Range<usize>) -> usize {
+    range.start + text[range].trim_end().len()
+}
+
+/// The last of `candidates` that `fits`, assuming that `fits` is true for
+/// the candidates before some point and false after it.
+#[allow(clippy::result_large_err)]
+fn last_fitting<T: Copy>(
+    candidates: &[T],
+    mut fits: impl FnMut(T) -> Result<bool, ImageFontRenderError>,
+) -> Result<Option<T>, ImageFontRenderError> {
+    let (mut low, mut high) = (0, candidates.len());
+    while low < high {
+        let mid = (low + high) / 2;
+        if fits(candidates[mid])? {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    Ok(low.checked_sub(1).map(|i| candidates[i]))
+}
+
+/// Picks the biggest font height at which the text fits in `fit.size`, and
+/// returns a copy of the text that uses it. Lines are wrapped to the width
+/// of the box (or its height, for vertical text) too.
+#[allow(clippy::result_large_err)]
+pub(crate) fn fit_text(
+    font: &ImageFont,
+    image_font_text: &ImageFontText,
+    fit: TextFit,
+) -> Result<ImageFontText, ImageFontRenderError> {
+    let line_height = font.line_height.max(1);
+    let limit = match image_font_text.writing_mode {
+        WritingMode::Horizontal => fit.size.x,
+        WritingMode::VerticalRl | WritingMode::VerticalLr => fit.size.y,
+    };
+    let mut fitted = ImageFontText {
+        max_width: Some(
+            image_font_text
+                .max_width
+                .map_or(limit, |max| max.min(limit)),
+        ),
+        overflow: TextOverflow::Wrap,
+        scale: None,
+        fit: None,
+        ..image_font_text.clone()
+    };
+    let font_heights: Vec<u32> = match fit.integer_scale {
+        true => (1..=fit.size.y / line_height)
+            .map(|scale| scale * line_height)
+            .collect(),
+        false => (1..=fit.size.y).collect(),
+    };
+
+    let padding = image_font_text.effects.padding();
+    let font_height = last_fitting(&font_heights, |font_height| {
+        fitted.font_height = Some(font_height as f32);
+        let size = layout_text(font, &fitted)?.bounds(font, padding).size();
+        let size = scaled_size(size.as_uvec2(), font_height as f32 / line_height as f32);
+        Ok(size.x <= fit.size.x && size.y <= fit.size.y)
+    })?;
+    // if even the smallest size is too big, that's what we use
+    let smallest = font_heights.first().copied().unwrap_or(line_height);
+    fitted.font_height = Some(font_height.unwrap_or(smallest) as f32);
+    Ok(fitted)
+}
+
+impl TextLayout<'_> {
+    /// The area to render, in unscaled pixels. This covers the area the pen
+    /// moved over and every glyph, plus `padding` on each side.
+    pub(crate) fn bounds(&self, font: &ImageFont, padding: IVec2) -> IRect {
+        let mut min = IVec2::ZERO;
+        let mut max = self.size;
+        for glyph in &self.glyphs {
+            let size = font.layout.textures[glyph.index].size().ceil().as_ivec2();
+            min = min.min(glyph.position);
+            max = max.max(glyph.position + size);
+        }
+        IRect::from_corners(min - padding, max + padding)
+    }
+}
+
+/// Lays out single lines of text.
+struct LineLayouter<'a, 'c> {
+    font: &'a ImageFont,
+    /// Where each grapheme cluster of the text starts.
+    clusters: &'c [usize],
+    vertical: bool,
+    tab_width: i32,
+    /// Width of a column in vertical text: the widest advance in the font.
+    /// Zero in horizontal text, where it isn't used, to avoid scanning every
+    /// glyph.
+    column_width: i32,
+}
+
+/// A line ready to be placed.
+type Line<'t> = (Vec<Piece<'t>>, TextDirection);
+
+impl<'a> LineLayouter<'a, '_> {
+    /// Places the glyphs of a line, returning how far the pen moved. `origin`
+    /// is the y coordinate of horizontal lines, or the x coordinate of
+    /// vertical ones.
+    #[allow(clippy::result_large_err)]
+    fn place(
+        &self,
+        pieces: &[Piece],
+        direction: TextDirection,
+        line: usize,
+        origin: i32,
+        placed: &mut Vec<PlacedGlyph<'a>>,
+    ) -> Result<i32, ImageFontRenderError> {
+        let font = self.font;
+        let mut pen = 0;
+        for piece in pieces {
+            if piece.after_tab {
+                pen = next_tab_stop(pen, self.tab_width);
+            }
+            // there's no such thing as vertical kerning, and bidi reordering
+            // only makes sense for horizontal lines
+            let mut glyphs = match self.vertical {
+                true => font.glyphs(piece.text)?,
+                false => font.visual_glyphs(piece.text, direction)?,
+            };
+            if piece.ellipsis {
+                for glyph in &mut glyphs {
+                    glyph.offset = 0;
+                }
+            }
+            let source = (piece.start, line, self.clusters);
+            if self.vertical {
+                let column_width = self.column_width;
+                place_glyphs(font, glyphs, source, placed, |glyph| {
+                    let metrics = font.metrics[glyph.index];
+                    let origin = IVec2::new(origin + (column_width - metrics.advance) / 2, pen);
+                    pen += metrics.vertical_advance;
+                    origin
+                });
+            } else {
+                let mut previous = None;
+                place_glyphs(font, glyphs, source, placed, |glyph| {
+                    if let Some(previous) = previous {
+                        pen += font.kerning(previous, glyph.key);
+                    }
+                    let origin = IVec2::new(pen, origin);
+                    pen += font.metrics[glyph.index].advance;
+                    previous = Some(glyph.key);
+                    origin
+                });
+            }
+        }
+        Ok(pen)
+    }
+
+    /// Whether the pieces fit within `limit` pixels.
+    #[allow(clippy::result_large_err)]
+    fn fits(
+        &self,
+        pieces: &[Piece],
+        direction: TextDirection,
+        limit: i32,
+    ) -> Result<bool, ImageFontRenderError> {
+        Ok(self.place(pieces, direction, 0, 0, &mut vec![])? <= limit)
+    }
+
+    /// Turns the paragraph at `range` into lines that fit within `limit`
+    /// pixels.
+    #[allow(clippy::result_large_err)]
+    fn fit<'t>(
+        &self,
+        text: &'t str,
+        range: Range<usize>,
+        direction: TextDirection,
+        limit: i32,
+        overflow: TextOverflow,
+        lines: &mut Vec<Line<'t>>,
+    ) -> Result<(), ImageFontRenderError> {
+        let whole = pieces(text, range.clone());
+        if self.fits(&whole, direction, limit)? {
+            lines.push((whole, direction));
+            return Ok(());
+        }
+        // where each grapheme cluster in the paragraph ends
+        let ends = |range: Range<usize>| -> Vec<usize> {
+            self.clusters
+                .iter()
+                .copied()
+                .filter(|&end| end > range.start && end < range.end)
+                .chain([range.end])
+                .collect()
+        };
+
+        match overflow {
+            TextOverflow::Wrap => {
+                let mut start = range.start;
+                while start < range.end {
+                    let rest = pieces(text, start..range.end);
+                    if self.fits(&rest, direction, limit)? {
+                        lines.push((rest, direction));
+                        break;
+                    }
+                    let ends = ends(start..range.end);
+                    // break after whitespace if possible, and in the middle
+                    // of a word if it's too long for a line by itself
+                    let breaks: Vec<usize> = ends
+                        .iter()
+                        .copied()
+                        .filter(|&end| text[..end].ends_with(char::is_whitespace))
+                        .collect();
+                    let end = match last_fitting(&breaks, |end| {
+                        self.fits(
+                            &pieces(text, start..trim_end(text, start..end)),
+                            direction,
+                            limit,
+                        )
+                    })? {
+                        Some(end) => end,
+                        None => last_fitting(&ends, |end| {
+                            self.fits(&pieces(text, start..end), direction, limit)
+                        })?
+                        // even a single cluster is too long, so give it a
+                        // line of its own
+                        .unwrap_or(ends[0]),
+                    };
+                    lines.push((pieces(text, start..trim_end(text, start..end)), direction));
+                    start = range.end - text[end..range.end].trim_start().len();
+                }
+            }
+            TextOverflow::Clip => {
+                let end = last_fitting(&ends(range.clone()), |end| {
+                    self.fits(&pieces(text, range.start..end), direction, limit)
+                })?;
+                lines.push((
+                    pieces(text, range.start..end.unwrap_or(range.start)),
+                    direction,
+                ));
+            }
+            TextOverflow::Ellipsis => {
+                let with_ellipsis = |end: usize| {
+                    let mut line = pieces(text, range.start..trim_end(text, range.start..end));
+                    line.push(ellipsis(self.font, end));
+                    line
+                };
+                let end = last_fitting(&ends(range.clone()), |end| {
+                    self.fits(&with_ellipsis(end), direction, limit)
+                })?;
+                lines.push((with_ellipsis(end.unwrap_or(range.start)), direction));
+            }
+            TextOverflow::EllipsisMiddle => {
+                let starts: Vec<usize> = self
+                    .clusters
+                    .iter()
+                    .copied()
+                    .filter(|&start| range.contains(&start))
+                    .collect();
+                // keeps the first and last `kept / 2` clusters, giving the
+                // start the extra one
+                let with_ellipsis = |kept: usize| {
+                    let head_end = starts.get(kept.div_ceil(2)).copied().unwrap_or(range.end);
+                    let tail_start = match kept / 2 {
+                        0 => range.end,
+                        tail => starts[starts.len() - tail],
+                    };
+                    let tail_start = range.end - text[tail_start..range.end].trim_start().len();
+                    let mut line = pieces(text, range.start..trim_end(text, range.start..head_end));
+                    line.push(ellipsis(self.font, head_end));
+                    line.extend(pieces(text, tail_start..range.end));
+                    line
+                };
+                let counts: Vec<usize> = (0..starts.len()).collect();
+                let kept = last_fitting(&counts, |kept| {
+                    self.fits(&with_ellipsis(kept), direction, limit)
+                })?;
+                lines.push((with_ellipsis(kept.unwrap_or(0)), direction));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The distance between tab stops in pixels, along the direction lines run
+/// in.
+fn tab_width(font: &ImageFont, tab_stops: TabStops, vertical: bool) -> i32 {
+    let advance = |metrics: &GlyphMetrics| match vertical {
+        true => metrics.vertical_advance,
+        false => metrics.advance,
+    };
+    match tab_stops {
+        TabStops::Pixels(pixels) => pixels as i32,
+        TabStops::Glyphs(count) => {
+            let space = match font.index_map.get(" ") {
+                Some(&index) => advance(&font.metrics[index]),
+                None => font.metrics.iter().map(advance).max().unwrap_or(0),
+            };
+            count as i32 * space
+        }
+    }
+}
+
+/// The first tab stop after `pen`.
+fn next_tab_stop(pen: i32, tab_width: i32) -> i32 {
+    if tab_width <= 0 {
+        return pen;
+    }
+    (pen.div_euclid(tab_width) + 1) * tab_width
+}
+
+/// Places the glyphs of a piece of text. `advance` returns the origin of each
+/// glyph that isn't a combining mark and moves the pen past it; marks are
+/// drawn relative to the origin of the glyph before them instead.
+///
+/// `start` is where the piece starts in the text, `line` is the line it's on
+/// and `clusters` is where each grapheme cluster of the text starts.
+fn place_glyphs<'a>(
+    font: &ImageFont,
+    glyphs: Vec<GlyphRef<'a>>,
+    (start, line, clusters): (usize, usize, &[usize]),
+    placed: &mut Vec<PlacedGlyph<'a>>,
+    mut advance: impl FnMut(&GlyphRef<'a>) -> IVec2,
+) {
+    let mut base_origin = IVec2::ZERO;
+    for glyph in glyphs {
+        let origin = match glyph.mark_offset {
+            Some(mark_offset) => base_origin + mark_offset,
+            None => {
+                base_origin = advance(&glyph);
+                base_origin
+            }
+        };
+        placed.push(PlacedGlyph {
+            key: glyph.key,
+            index: glyph.index,
+            position: origin + font.metrics[glyph.index].offset,
+            cluster: clusters.partition_point(|&cluster| cluster <= start + glyph.offset) - 1,
+            line,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::HashMap;
+
+    use super::*;
+
+    /// A font where every glyph is a single pixel wide, so widths are counts
+    /// of glyphs.
+    fn font(keys: &str) -> ImageFont {
+        let mut layout = TextureAtlasLayout::new_empty(Vec2::new(keys.len() as f32, 1.0));
+        let mut index_map = HashMap::new();
+        let mut metrics = vec![];
+        for (i, key) in keys.graphemes(true).enumerate() {
+            let rect = Rect::new(i as f32, 0.0, i as f32 + 1.0, 1.0);
+            index_map.insert(key.to_owned(), layout.add_texture(rect));
+            metrics.push(GlyphMetrics::for_rect(rect));
+        }
+        let pages = vec![0; metrics.len()];
+        ImageFont::new(layout, vec![Handle::default()], pages, index_map, metrics)
+    }
+
+    /// The lines `text` is split into to fit in `limit` pixels.
+    fn fit(font: &ImageFont, text: &str, limit: i32, overflow: TextOverflow) -> Vec<String> {
+        let clusters: Vec<usize> = text.grapheme_indices(true).map(|(i, _)| i).collect();
+        let layouter = LineLayouter {
+            font,
+            clusters: &clusters,
+            vertical: false,
+            tab_width: 0,
+            column_width: 0,
+        };
+        let mut lines = vec![];
+        layouter
+            .fit(
+                text,
+                0..text.len(),
+                TextDirection::Ltr,
+                limit,
+                overflow,
+                &mut lines,
+            )
+            .unwrap();
+        lines
+            .into_iter()
+            .map(|(pieces, _)| pieces.iter().map(|piece| piece.text).collect())
+            .collect()
+    }
+
+    #[test]
+    fn lines_that_fit_are_left_alone() {
+        let font = font("abcdef …");
+        for overflow in [
+            TextOverflow::Clip,
+            TextOverflow::Wrap,
+            TextOverflow::Ellipsis,
+            TextOverflow::EllipsisMiddle,
+        ] {
+            assert_eq!(fit(&font, "abc def", 7, overflow), ["abc def"]);
+        }
+    }
+
+    #[test]
+    fn clip() {
+        let font = font("abcdef ");
+        assert_eq!(fit(&font, "abc def", 5, TextOverflow::Clip), ["abc d"]);
+        assert_eq!(fit(&font, "abc", 0, TextOverflow::Clip), [""]);
+    }
+
+    #[test]
+    fn wrap_breaks_after_whitespace() {
+        let font = font("abcdef ");
+        assert_eq!(
+            fit(&font, "ab cd ef", 5, TextOverflow::Wrap),
+            ["ab cd", "ef"]
+        );
+        assert_eq!(
+            fit(&font, "ab cd ef", 4, TextOverflow::Wrap),
+            ["ab", "cd", "ef"]
+        );
+    }
+
+    #[test]
+    fn wrap_breaks_long_words() {
+        let font = font("abcdef ");
+        assert_eq!(
+            fit(&font, "abcdef a", 4, TextOverflow::Wrap),
+            ["abcd", "ef a"]
+        );
+        // a glyph wider than the limit still gets a line
+        assert_eq!(fit(&font, "ab", 0, TextOverflow::Wrap), ["a", "b"]);
+    }
+
+    #[test]
+    fn ellipsis_at_end() {
+        let font = font("abcdef …");
+        assert_eq!(fit(&font, "abcdef", 4, TextOverflow::Ellipsis), ["abc…"]);
+        // whitespace before the ellipsis is left out
+        assert_eq!(fit(&font, "ab cdef", 4, TextOverflow::Ellipsis), ["ab…"]);
+    }
+
+    #[test]
+    fn ellipsis_falls_back_to_dots() {
+        let font = font("abcdef .");
+        assert_eq!(fit(&font, "abcdef", 5, TextOverflow::Ellipsis), ["ab..."]);
+    }
+
+    #[test]
+    fn ellipsis_in_middle() {
+        let font = font("abcdefgh …");
+        assert_eq!(
+            fit(&font, "abcdefgh", 5, TextOverflow::EllipsisMiddle),
+            ["ab…gh"]
+        );
+        // the start gets the extra cluster
+        assert_eq!(
+            fit(&font, "abcdefgh", 4, TextOverflow::EllipsisMiddle),
+            ["ab…h"]
+        );
+    }
+}
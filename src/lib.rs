@@ -1,24 +1,42 @@
 #![doc = include_str!("../README.md")]
 
+pub mod baked;
+#[cfg(feature = "bdf")]
+pub mod bdf;
+pub mod bmfont;
+pub mod codepage;
+pub mod effects;
+mod filter;
+pub mod layout;
 pub mod loader;
+mod packing;
+pub mod reveal;
+pub mod snap;
+
+use std::borrow::Cow;
 
 #[cfg(feature = "ui")]
 use bevy::ui::widget::update_image_content_size_system;
 use bevy::{
     prelude::*,
     render::{
+        camera::CameraUpdateSystem,
         render_asset::RenderAssetUsages,
         render_resource::{Extent3d, TextureDimension, TextureFormat},
         texture::ImageSampler,
     },
+    transform::TransformSystem,
     utils::{HashMap, HashSet},
 };
 use derive_setters::Setters;
-use image::{
-    imageops::{self, FilterType},
-    GenericImage, GenericImageView, ImageBuffer, ImageError, Rgba,
-};
+use image::{imageops, GenericImageView, ImageBuffer, ImageError, Rgba};
+use layout::{GlyphLayout, ImageFontTextLayout};
+use loader::{ImageFontLoadError, ImageFontLoaderSettings};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use unicode_bidi::{Level, ParagraphBidiInfo};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+use unicode_segmentation::UnicodeSegmentation;
 
 #[derive(Default)]
 pub struct ImageFontPlugin;
@@ -28,13 +46,47 @@ impl Plugin for ImageFontPlugin {
         app.init_asset::<ImageFont>()
             .add_systems(
                 PostUpdate,
-                (mark_changed_fonts_as_dirty, render_sprites)
+                (
+                    reveal::reveal_text,
+                    effects::animate_text_effects,
+                    mark_changed_fonts_as_dirty,
+                    render_sprites,
+                )
                     .chain()
                     .in_set(ImageFontSet),
             )
             .init_asset_loader::<loader::ImageFontLoader>()
+            .init_asset_loader::<bmfont::BmFontLoader>()
+            .init_asset_loader::<baked::BakedImageFontLoader>()
             .register_type::<ImageFont>()
-            .register_type::<ImageFontText>();
+            .register_type::<GlyphMetrics>()
+            .register_type::<MissingGlyphPolicy>()
+            .register_type::<TextNormalization>()
+            .register_type::<TextDirection>()
+            .register_type::<WritingMode>()
+            .register_type::<TabStops>()
+            .register_type::<TextOverflow>()
+            .register_type::<TextFit>()
+            .register_type::<ScaleFilter>()
+            .register_type::<ImageFontText>()
+            .register_type::<reveal::TypewriterReveal>()
+            .register_type::<effects::TextEffects>()
+            .register_type::<effects::TextEffect>()
+            .register_type::<ImageFontTextLayout>()
+            .add_event::<reveal::CharacterRevealed>();
+        // kept out of `ImageFontSet`, so that ordering the set before transform
+        // propagation doesn't make a cycle
+        app.add_systems(
+            PostUpdate,
+            snap::snap_to_pixels
+                .after(render_sprites)
+                .after(TransformSystem::TransformPropagate)
+                .after(CameraUpdateSystem),
+        )
+        .register_type::<snap::PixelSnap>();
+        #[cfg(feature = "bdf")]
+        app.init_asset_loader::<bdf::BdfLoader>();
+        baked::register_processors(app);
         #[cfg(feature = "ui")]
         app.add_systems(
             PostUpdate,
@@ -51,35 +103,387 @@ impl Plugin for ImageFontPlugin {
 pub struct ImageFontSet;
 
 /// An image font as well as the mapping of characters to regions inside it.
+///
+/// A font can be split across several textures ('pages'), which is necessary
+/// for fonts with more glyphs than fit in a single texture.
 #[derive(Debug, Clone, Reflect, Asset)]
 pub struct ImageFont {
+    /// The rects of all glyphs on all pages. Each rect is relative to the
+    /// texture of the page the glyph is on.
     pub layout: TextureAtlasLayout,
-    pub texture: Handle<Image>,
-    /// The glyph used to render `c` is contained in the part of the image
-    /// pointed to by `atlas.textures[index_map[c]]`.
-    pub index_map: HashMap<char, usize>,
+    /// The texture of each page.
+    pub textures: Vec<Handle<Image>>,
+    /// The page each glyph is on; `textures[pages[i]]` contains
+    /// `layout.textures[i]`.
+    pub pages: Vec<usize>,
+    /// The glyph used to render `key` is contained in the part of the image
+    /// pointed to by `atlas.textures[index_map[key]]`. Keys are usually
+    /// single characters, but can be whole grapheme clusters such as flags or
+    /// letters with combining accents, or ligatures spanning several clusters
+    /// such as `->`.
+    ///
+    /// If you add keys after the font is loaded, call
+    /// [`Self::update_max_key_len`] afterwards.
+    pub index_map: HashMap<String, usize>,
+    /// See [`Self::max_key_len`].
+    max_key_len: usize,
+    /// How to position each glyph relative to the pen; `metrics[i]` goes with
+    /// `layout.textures[i]`.
+    pub metrics: Vec<GlyphMetrics>,
+    /// Extra horizontal adjustment applied between two consecutive characters,
+    /// in pixels. Usually negative. Only applies between glyphs whose keys are
+    /// single characters.
+    pub kerning: HashMap<(char, char), i32>,
+    /// Extra offset for combining marks that are drawn on top of another
+    /// glyph, in pixels. See [`ImageFontSettings::mark_offsets`].
+    ///
+    /// [`ImageFontSettings::mark_offsets`]: loader::ImageFontSettings::mark_offsets
+    pub mark_offsets: HashMap<char, IVec2>,
+    /// Height of a line of text in pixels. Rendered text is always at least
+    /// this tall so that different strings in the same font line up.
+    pub line_height: u32,
+    /// Sampler used for images rendered with this font.
+    #[reflect(ignore)]
+    pub sampler: ImageSampler,
+    /// Height to render text at if [`ImageFontText::font_height`] isn't set.
+    pub default_font_height: Option<f32>,
+    /// What to do with characters that the font has no glyph for.
+    pub missing_glyph: MissingGlyphPolicy,
 }
 
-impl ImageFont {
-    fn from_char_map(texture: Handle<Image>, size: UVec2, char_map: &HashMap<char, Rect>) -> Self {
-        let mut index_map = HashMap::new();
-        let mut layout = TextureAtlasLayout::new_empty(size.as_vec2());
-        for (i, (&c, &rect)) in char_map.iter().enumerate() {
-            index_map.insert(c, i);
-            layout.add_texture(rect);
-        }
+/// What to do when rendering a character that the font has no glyph for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum MissingGlyphPolicy {
+    /// Leave the character out.
+    #[default]
+    Skip,
+    /// Render the given character instead, typically something like `?`. If
+    /// the font doesn't have that either, the character is left out.
+    Replace(char),
+    /// Fail to render the text.
+    Error,
+}
+
+/// Placement information for a single glyph.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect)]
+pub struct GlyphMetrics {
+    /// Offset from the pen position (the top-left corner of the current
+    /// character cell) to the top-left corner of the glyph.
+    pub offset: IVec2,
+    /// How far to move the pen to the right after drawing this glyph.
+    pub advance: i32,
+    /// How far to move the pen down after drawing this glyph in vertical
+    /// text.
+    pub vertical_advance: i32,
+}
+
+impl GlyphMetrics {
+    /// Metrics for a glyph that occupies its entire rect, which is the case
+    /// for fonts laid out by hand in an image.
+    fn for_rect(rect: Rect) -> Self {
         Self {
+            offset: IVec2::ZERO,
+            advance: rect.width().ceil() as i32,
+            vertical_advance: rect.height().ceil() as i32,
+        }
+    }
+}
+
+impl ImageFont {
+    /// Builds a font by hand. Each glyph's metrics go with the rect at the
+    /// same position in `layout`, and `pages` says which of the `textures`
+    /// it's on. The line height is that of the tallest glyph; the rest of the
+    /// fields start out empty or at their defaults and can be set afterwards.
+    pub fn new(
+        layout: TextureAtlasLayout,
+        textures: Vec<Handle<Image>>,
+        pages: Vec<usize>,
+        index_map: HashMap<String, usize>,
+        metrics: Vec<GlyphMetrics>,
+    ) -> Self {
+        let line_height = layout
+            .textures
+            .iter()
+            .map(|rect| rect.height().ceil() as u32)
+            .max()
+            .unwrap_or(0);
+        let mut font = Self {
             layout,
-            texture,
+            textures,
+            pages,
             index_map,
+            max_key_len: 0,
+            metrics,
+            kerning: HashMap::new(),
+            mark_offsets: HashMap::new(),
+            line_height,
+            sampler: ImageSampler::default(),
+            default_font_height: None,
+            missing_glyph: MissingGlyphPolicy::default(),
+        };
+        font.update_max_key_len();
+        font
+    }
+
+    /// Builds a font out of pages, each of which is a texture, its size, and
+    /// where the characters are in it. A character can only be on one page,
+    /// unless `settings.lenient` is set, in which case the last page wins.
+    #[allow(clippy::result_large_err)]
+    fn from_char_maps(
+        pages: Vec<(Handle<Image>, UVec2, HashMap<String, Rect>)>,
+        settings: &ImageFontLoaderSettings,
+    ) -> Result<Self, ImageFontLoadError> {
+        let max_size = pages
+            .iter()
+            .map(|(_, size, _)| *size)
+            .fold(UVec2::ZERO, UVec2::max);
+        let mut font = Self {
+            layout: TextureAtlasLayout::new_empty(max_size.as_vec2()),
+            textures: vec![],
+            pages: vec![],
+            index_map: HashMap::new(),
+            max_key_len: 0,
+            metrics: vec![],
+            kerning: HashMap::new(),
+            mark_offsets: HashMap::new(),
+            line_height: 0,
+            sampler: settings.sampler.clone(),
+            default_font_height: settings.font_height,
+            missing_glyph: settings.missing_glyph,
+        };
+        for (page, (texture, _, char_map)) in pages.into_iter().enumerate() {
+            font.textures.push(texture);
+            let mut char_map: Vec<_> = char_map.into_iter().collect();
+            char_map.sort_by(|(a, _), (b, _)| a.cmp(b));
+            for (key, rect) in char_map {
+                let index = font.layout.add_texture(rect);
+                // spaces are allowed to repeat, since they're used as padding
+                if let Some(first) = font.index_map.insert(key.clone(), index) {
+                    if key != " " {
+                        loader::check(
+                            settings.lenient,
+                            ImageFontLoadError::DuplicatePageGlyph {
+                                key,
+                                first_page: font.pages[first],
+                                second_page: page,
+                            },
+                        )?;
+                    }
+                }
+                font.pages.push(page);
+                font.metrics.push(GlyphMetrics::for_rect(rect));
+                font.line_height = font.line_height.max(rect.height().ceil() as u32);
+            }
+        }
+        Ok(font)
+    }
+
+    /// Adds the NFC and NFD forms of every key to the index map, so that text
+    /// finds the glyph whichever way it was normalized, then updates
+    /// [`Self::max_key_len`].
+    pub(crate) fn add_normalized_keys(&mut self) {
+        let keys: Vec<(String, usize)> = self
+            .index_map
+            .iter()
+            .map(|(key, &index)| (key.clone(), index))
+            .collect();
+        for (key, index) in keys {
+            for normalized in [key.nfc().collect::<String>(), key.nfd().collect()] {
+                self.index_map.entry(normalized).or_insert(index);
+            }
+        }
+        self.update_max_key_len();
+    }
+
+    /// Length in bytes of the longest key in [`Self::index_map`], which
+    /// limits how far ahead rendering looks for ligatures.
+    pub fn max_key_len(&self) -> usize {
+        self.max_key_len
+    }
+
+    /// Recomputes [`Self::max_key_len`] from [`Self::index_map`]. Needed
+    /// after adding keys to the index map by hand, or glyphs with longer
+    /// keys than before won't be found.
+    pub fn update_max_key_len(&mut self) {
+        self.max_key_len = self.index_map.keys().map(String::len).max().unwrap_or(0);
+    }
+
+    /// Horizontal adjustment to apply between the glyphs for `left` and
+    /// `right`.
+    fn kerning(&self, left: &str, right: &str) -> i32 {
+        let mut left = left.chars();
+        let mut right = right.chars();
+        match (left.next(), left.next(), right.next(), right.next()) {
+            (Some(left), None, Some(right), None) => {
+                self.kerning.get(&(left, right)).copied().unwrap_or(0)
+            }
+            _ => 0,
         }
     }
 
-    fn filter_string(&self, s: impl AsRef<str>) -> String {
-        s.as_ref()
-            .chars()
-            .filter(|c| self.index_map.contains_key(c))
-            .collect()
+    /// The longest prefix of `s` that the font has a glyph for, along with the
+    /// glyph's index.
+    fn longest_prefix(&self, s: &str) -> Option<(&str, usize)> {
+        s.char_indices()
+            .map(|(i, c)| i + c.len_utf8())
+            .rev()
+            .find_map(|end| {
+                let (key, &index) = self.index_map.get_key_value(&s[..end])?;
+                Some((key.as_str(), index))
+            })
+    }
+
+    /// Splits the string into the glyphs used to render it.
+    ///
+    /// At each point, the longest run of whole grapheme clusters that the font
+    /// has a glyph for is used, which is how ligatures like `->` or `fi` get
+    /// picked over their individual characters. A cluster that doesn't start
+    /// any such run is decomposed (using Unicode NFD) and drawn with the glyph
+    /// for the longest prefix of it that the font has, followed by glyphs for
+    /// the rest of the cluster. Combining marks after the first glyph are
+    /// drawn on top of it. [`Self::missing_glyph`] applies to characters that
+    /// don't start any glyph.
+    #[allow(clippy::result_large_err)]
+    fn glyphs(&self, s: &str) -> Result<Vec<GlyphRef<'_>>, ImageFontRenderError> {
+        let boundaries: Vec<usize> = s
+            .grapheme_indices(true)
+            .map(|(i, _)| i)
+            .chain([s.len()])
+            .collect();
+        let mut glyphs = vec![];
+        let mut cluster = 0;
+        while cluster + 1 < boundaries.len() {
+            let start = boundaries[cluster];
+            // no point in looking for sequences longer than any key
+            let sequence = (cluster + 1..boundaries.len())
+                .take_while(|&end| boundaries[end] - start <= self.max_key_len)
+                .filter_map(|end| {
+                    let (key, &index) = self.index_map.get_key_value(&s[start..boundaries[end]])?;
+                    Some((end, key.as_str(), index))
+                })
+                .last();
+            if let Some((end, key, index)) = sequence {
+                glyphs.push(GlyphRef::new(key, index, start));
+                cluster = end;
+            } else {
+                self.cluster_glyphs(&s[start..boundaries[cluster + 1]], start, &mut glyphs)?;
+                cluster += 1;
+            }
+        }
+        Ok(glyphs)
+    }
+
+    /// Like [`Self::glyphs`], but returns the glyphs in the order they should
+    /// be drawn in from left to right, according to the Unicode bidirectional
+    /// algorithm. Each run of text with a single direction is split into
+    /// glyphs on its own, and right-to-left runs are reversed (keeping
+    /// combining marks with their base glyph).
+    #[allow(clippy::result_large_err)]
+    fn visual_glyphs(
+        &self,
+        text: &str,
+        direction: TextDirection,
+    ) -> Result<Vec<GlyphRef<'_>>, ImageFontRenderError> {
+        let level = match direction {
+            TextDirection::Auto => None,
+            TextDirection::Ltr => Some(Level::ltr()),
+            TextDirection::Rtl => Some(Level::rtl()),
+        };
+        let bidi = ParagraphBidiInfo::new(text, level);
+        if !bidi.has_rtl() {
+            return self.glyphs(text);
+        }
+
+        let (levels, runs) = bidi.visual_runs(0..text.len());
+        let mut glyphs = vec![];
+        for run in runs {
+            let mut run_glyphs = self.glyphs(&text[run.clone()])?;
+            for glyph in &mut run_glyphs {
+                glyph.offset += run.start;
+            }
+            if !levels[run.start].is_rtl() {
+                glyphs.extend(run_glyphs);
+                continue;
+            }
+            // each group is a glyph followed by the marks drawn on it
+            let mut groups: Vec<Vec<GlyphRef>> = vec![];
+            for glyph in run_glyphs {
+                match groups.last_mut() {
+                    Some(group) if glyph.mark_offset.is_some() => group.push(glyph),
+                    _ => groups.push(vec![glyph]),
+                }
+            }
+            glyphs.extend(groups.into_iter().rev().flatten());
+        }
+        Ok(glyphs)
+    }
+
+    /// Adds the glyphs for a grapheme cluster that the font has no glyph for as
+    /// a whole. `offset` is where the cluster starts in the text.
+    #[allow(clippy::result_large_err)]
+    fn cluster_glyphs<'a>(
+        &'a self,
+        cluster: &str,
+        offset: usize,
+        glyphs: &mut Vec<GlyphRef<'a>>,
+    ) -> Result<(), ImageFontRenderError> {
+        let decomposed: String = cluster.nfd().collect();
+        let mut rest = decomposed.as_str();
+        let mut has_base = false;
+        while let Some(c) = rest.chars().next() {
+            if let Some((key, index)) = self.longest_prefix(rest) {
+                let mut glyph = GlyphRef::new(key, index, offset);
+                if has_base && is_combining_mark(c) && key.len() == c.len_utf8() {
+                    glyph.mark_offset =
+                        Some(self.mark_offsets.get(&c).copied().unwrap_or_default());
+                }
+                glyphs.push(glyph);
+                has_base = true;
+                rest = &rest[key.len()..];
+                continue;
+            }
+            match self.missing_glyph {
+                MissingGlyphPolicy::Skip => {}
+                MissingGlyphPolicy::Replace(replacement) => {
+                    if let Some((key, &index)) = self
+                        .index_map
+                        .get_key_value(replacement.encode_utf8(&mut [0; 4]) as &str)
+                    {
+                        glyphs.push(GlyphRef::new(key, index, offset));
+                    }
+                }
+                MissingGlyphPolicy::Error => {
+                    return Err(ImageFontRenderError::MissingGlyph(cluster.to_owned()))
+                }
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+        Ok(())
+    }
+}
+
+/// A glyph to draw, as decided by [`ImageFont::glyphs`].
+#[derive(Debug, Clone, Copy)]
+struct GlyphRef<'a> {
+    key: &'a str,
+    index: usize,
+    /// Byte offset of the text the glyph stands for, relative to the start
+    /// of the string the glyph was found in. All glyphs for one grapheme
+    /// cluster share the offset of the cluster.
+    offset: usize,
+    /// For combining marks drawn on top of the previous glyph, the offset
+    /// from where that glyph was drawn.
+    mark_offset: Option<IVec2>,
+}
+
+impl<'a> GlyphRef<'a> {
+    fn new(key: &'a str, index: usize, offset: usize) -> Self {
+        Self {
+            key,
+            index,
+            offset,
+            mark_offset: None,
+        }
     }
 }
 
@@ -89,10 +493,214 @@ impl ImageFont {
 pub struct ImageFontText {
     pub text: String,
     pub font: Handle<ImageFont>,
-    /// If set, overrides the height the font is rendered at. This should be an
-    /// integer multiple of the 'native' height if you want pixel accuracy,
-    /// but we allow float values for things like animations.
+    /// If set, overrides the height the font is rendered at (including the
+    /// font's [`ImageFont::default_font_height`]). This is the height of a
+    /// single line, so text with several lines comes out taller. This should
+    /// be an integer multiple of the font's [`ImageFont::line_height`] if you
+    /// want pixel accuracy (or use [`Self::scale`]), but we allow float
+    /// values for things like animations.
     pub font_height: Option<f32>,
+    /// If set, every pixel of the font is drawn as a `scale` by `scale`
+    /// square, which is always pixel-perfect. Overrides
+    /// [`Self::font_height`].
+    pub scale: Option<u32>,
+    /// How to resize the text when it's rendered at a different size than
+    /// the font's native one.
+    pub filter: ScaleFilter,
+    /// How to normalize the text before looking up glyphs.
+    pub normalization: TextNormalization,
+    /// The base direction of the text, which decides the order of runs of
+    /// text in different directions (such as English words in a Hebrew
+    /// sentence). Ignored for vertical text.
+    pub direction: TextDirection,
+    /// Whether lines run horizontally or vertically.
+    pub writing_mode: WritingMode,
+    /// Where each `\t` in the text moves the pen to.
+    pub tab_stops: TabStops,
+    /// If set, only this many grapheme clusters (roughly, characters) of the
+    /// text are drawn. The image is the same size as for the whole text, so
+    /// revealing text bit by bit doesn't move anything around. See
+    /// [`reveal::TypewriterReveal`] for animating this.
+    pub visible_chars: Option<usize>,
+    /// Animated effects applied to the glyphs, such as wavy or shaking text.
+    pub effects: effects::TextEffects,
+    /// If set, the longest a line can be in the rendered image, in pixels.
+    /// For vertical text this limits the height of columns instead. What
+    /// happens to lines that are too long is decided by [`Self::overflow`].
+    pub max_width: Option<u32>,
+    /// What to do with lines longer than [`Self::max_width`].
+    pub overflow: TextOverflow,
+    /// If set, the text is rendered as big as it can be while still fitting
+    /// in a box, overriding [`Self::font_height`].
+    pub fit: Option<TextFit>,
+}
+
+impl ImageFontText {
+    /// How much the text is scaled up (or down) when rendered, if at all.
+    pub(crate) fn render_scale(&self, font: &ImageFont) -> Option<f32> {
+        if let Some(scale) = self.scale {
+            return Some(scale as f32);
+        }
+        let font_height = self.font_height.or(font.default_font_height)?;
+        // scale so that a single line is `font_height` tall
+        Some(font_height / font.line_height.max(1) as f32)
+    }
+}
+
+/// How to resize an [`ImageFontText`]; see [`ImageFontText::filter`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum ScaleFilter {
+    /// Use the nearest pixel, which keeps pixel art crisp. Non-integer scales
+    /// make some pixels bigger than others, though.
+    #[default]
+    Nearest,
+    /// Blend neighboring pixels linearly.
+    Triangle,
+    /// Lanczos resampling with a window of 3, which is sharper than
+    /// [`Self::Triangle`] but can cause ringing around edges.
+    Lanczos,
+    /// Average every pixel that each output pixel covers. This is the best
+    /// way to shrink text, since [`Self::Nearest`] drops entire rows and
+    /// columns of pixels.
+    Area,
+    /// The Scale2x (or EPX) pixel art upscaler, which doubles the size of the
+    /// text while smoothing out diagonal edges. It's applied as many times
+    /// as fits, and any remaining scaling uses [`Self::Nearest`].
+    Scale2x,
+    /// Like [`Self::Scale2x`], but triples the size each time.
+    Scale3x,
+}
+
+/// A box to fit an [`ImageFontText`] into; see [`ImageFontText::fit`].
+///
+/// The text is wrapped to the width of the box (as with
+/// [`TextOverflow::Wrap`], whatever [`ImageFontText::overflow`] is), and
+/// rendered at the biggest whole-pixel font height at which it fits. If it
+/// doesn't fit even at the smallest height, it's rendered at that height
+/// anyway.
+///
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use extol_image_font::{ImageFontText, TextFit};
+/// let label = ImageFontText::default()
+///     .text("Einstellungen speichern")
+///     .fit(TextFit {
+///         size: UVec2::new(120, 32),
+///         integer_scale: true,
+///     });
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub struct TextFit {
+    /// Size of the box, in pixels.
+    pub size: UVec2,
+    /// Only render the text at whole multiples of the font's
+    /// [`ImageFont::line_height`], which keeps every pixel of the font the
+    /// same size.
+    pub integer_scale: bool,
+}
+
+/// What to do with lines of an [`ImageFontText`] that are longer than its
+/// [`max_width`](ImageFontText::max_width).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum TextOverflow {
+    /// Leave out whatever doesn't fit.
+    #[default]
+    Clip,
+    /// Break lines after whitespace. Words that are too long for a line by
+    /// themselves are broken wherever they need to be.
+    Wrap,
+    /// Cut the end off the line and put an ellipsis there. This uses the
+    /// font's `…` glyph if it has one, or `...` otherwise.
+    Ellipsis,
+    /// Cut out the middle of the line and put an ellipsis there, which keeps
+    /// things like file extensions visible.
+    EllipsisMiddle,
+}
+
+/// Tab stops for an [`ImageFontText`], for lining up columns of text such as
+/// inventory lists. Each `\t` moves the pen to the next tab stop after it.
+///
+/// Tab stops are measured in unscaled pixels from the start of the line (the
+/// top of the column for vertical text), and are always the same distance
+/// apart. The pieces of text between tabs are placed left to right, even in
+/// right-to-left text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum TabStops {
+    /// A tab stop every this many pixels.
+    Pixels(u32),
+    /// A tab stop every this many spaces, like in a terminal. Uses the
+    /// advance of the font's space glyph, or of its widest glyph if it
+    /// doesn't have one.
+    Glyphs(u32),
+}
+
+impl Default for TabStops {
+    fn default() -> Self {
+        TabStops::Glyphs(4)
+    }
+}
+
+/// Which way the lines of an [`ImageFontText`] run. Each `\n` in the text
+/// starts a new line (or column, for vertical text).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum WritingMode {
+    /// Glyphs go left to right (or right to left, see [`TextDirection`]) and
+    /// lines go top to bottom.
+    #[default]
+    Horizontal,
+    /// Glyphs go top to bottom, moving down by their
+    /// [`GlyphMetrics::vertical_advance`], and columns go right to left. This
+    /// is how Japanese and Chinese are traditionally written.
+    VerticalRl,
+    /// Glyphs go top to bottom and columns go left to right, which is handy
+    /// for labels on the side of a panel.
+    VerticalLr,
+}
+
+/// Base direction of an [`ImageFontText`].
+///
+/// Text is always laid out using the Unicode bidirectional algorithm, so
+/// Arabic and Hebrew render right-to-left while numbers and Latin text
+/// embedded in them stay left-to-right. Mirroring of characters like brackets
+/// isn't done; give the font separate glyphs if you need it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum TextDirection {
+    /// Use the direction of the first character with a strong direction, or
+    /// left-to-right if there isn't one.
+    #[default]
+    Auto,
+    /// Left-to-right.
+    Ltr,
+    /// Right-to-left.
+    Rtl,
+}
+
+/// Unicode normalization form to apply to an [`ImageFontText`]'s text.
+///
+/// The same accented letter can be written either precomposed (`é`) or as a
+/// base letter followed by a combining mark (`e\u{301}`). Fonts have glyphs for
+/// both forms of every key, so this rarely matters, but it decides which
+/// glyph is used when the font defines both.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum TextNormalization {
+    /// Normalization Form C, which prefers precomposed characters.
+    #[default]
+    Nfc,
+    /// Normalization Form D, which prefers decomposed characters.
+    Nfd,
+    /// Use the text as is.
+    None,
+}
+
+impl TextNormalization {
+    /// Normalizes the text using this form.
+    fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self {
+            TextNormalization::Nfc => text.nfc().collect::<String>().into(),
+            TextNormalization::Nfd => text.nfd().collect::<String>().into(),
+            TextNormalization::None => text.into(),
+        }
+    }
 }
 
 /// All the components you need to render image font text 'in the world'. If you
@@ -113,17 +721,27 @@ pub struct ImageFontUiBundle {
 
 /// System that renders each [`ImageFontText`] into the corresponding
 /// `Handle<Image>`. This is mainly for use with sprites.
+#[allow(clippy::type_complexity)]
 pub fn render_sprites(
-    mut query: Query<(&ImageFontText, &mut Handle<Image>), Changed<ImageFontText>>,
+    mut commands: Commands,
+    mut query: Query<
+        (
+            Entity,
+            &ImageFontText,
+            &mut Handle<Image>,
+            Has<ImageFontTextLayout>,
+        ),
+        Changed<ImageFontText>,
+    >,
     image_fonts: Res<Assets<ImageFont>>,
     mut images: ResMut<Assets<Image>>,
 ) {
-    for (image_font_text, mut image_handle) in &mut query {
+    for (entity, image_font_text, mut image_handle, rendered_before) in &mut query {
         debug!("Rendering [{}]", image_font_text.text);
-        // don't need to clear the old image since it'll be no longer live
-        match render_text(image_font_text, image_fonts.as_ref(), images.as_ref()) {
-            Ok(image) => {
-                *image_handle = images.add(image);
+        match render_text_with_layout(image_font_text, image_fonts.as_ref(), images.as_ref()) {
+            Ok((image, layout)) => {
+                store_image(&mut images, image_handle.as_mut(), image, rendered_before);
+                commands.entity(entity).insert(layout);
             }
             Err(e) => {
                 error!(
@@ -138,17 +756,27 @@ pub fn render_sprites(
 #[cfg(feature = "ui")]
 /// System that renders each [`ImageFontText`] into the corresponding
 /// [`UiImage`].
+#[allow(clippy::type_complexity)]
 pub fn render_ui_images(
-    mut query: Query<(&ImageFontText, &mut UiImage), Changed<ImageFontText>>,
+    mut commands: Commands,
+    mut query: Query<
+        (
+            Entity,
+            &ImageFontText,
+            &mut UiImage,
+            Has<ImageFontTextLayout>,
+        ),
+        Changed<ImageFontText>,
+    >,
     image_fonts: Res<Assets<ImageFont>>,
     mut images: ResMut<Assets<Image>>,
 ) {
-    for (image_font_text, mut ui_image) in &mut query {
+    for (entity, image_font_text, mut ui_image, rendered_before) in &mut query {
         debug!("Rendering [{}]", image_font_text.text);
-        // don't need to clear the old image since it'll be no longer live
-        match render_text(image_font_text, image_fonts.as_ref(), images.as_ref()) {
-            Ok(image) => {
-                ui_image.texture = images.add(image);
+        match render_text_with_layout(image_font_text, image_fonts.as_ref(), images.as_ref()) {
+            Ok((image, layout)) => {
+                store_image(&mut images, &mut ui_image.texture, image, rendered_before);
+                commands.entity(entity).insert(layout);
             }
             Err(e) => {
                 error!(
@@ -160,6 +788,23 @@ pub fn render_ui_images(
     }
 }
 
+/// Points `handle` at the newly rendered `image`. If the text was rendered
+/// before, `handle` is the image we rendered it to, which is overwritten
+/// instead of adding a new image every time the text changes. Otherwise,
+/// `handle` might be an image that isn't ours (such as the default white
+/// texture), so the image is added as a new asset.
+fn store_image(
+    images: &mut Assets<Image>,
+    handle: &mut Handle<Image>,
+    image: Image,
+    rendered_before: bool,
+) {
+    match images.get_mut(&*handle).filter(|_| rendered_before) {
+        Some(old_image) => *old_image = image,
+        None => *handle = images.add(image),
+    }
+}
+
 /// Errors that can show up during rendering.
 #[derive(Debug, Error)]
 #[non_exhaustive]
@@ -174,6 +819,8 @@ pub enum ImageFontRenderError {
     UnknownError,
     #[error("failed to copy from atlas")]
     CopyFailure(#[from] ImageError),
+    #[error("font has no glyph for {0:?}")]
+    MissingGlyph(String),
 }
 
 /// Renders the text inside the [`ImageFontText`] to a single output image. You
@@ -185,19 +832,53 @@ pub fn render_text(
     image_fonts: &Assets<ImageFont>,
     images: &Assets<Image>,
 ) -> Result<Image, ImageFontRenderError> {
+    render_text_with_layout(image_font_text, image_fonts, images).map(|(image, _)| image)
+}
+
+/// Like [`render_text`], but also returns where each glyph was drawn.
+#[allow(clippy::result_large_err)]
+pub fn render_text_with_layout(
+    image_font_text: &ImageFontText,
+    image_fonts: &Assets<ImageFont>,
+    images: &Assets<Image>,
+) -> Result<(Image, ImageFontTextLayout), ImageFontRenderError> {
     let image_font = image_fonts
         .get(&image_font_text.font)
         .ok_or(ImageFontRenderError::MissingImageFontAsset)?;
-    let font_texture = images
-        .get(&image_font.texture)
-        .ok_or(ImageFontRenderError::MissingTextureAsset)?;
+    let font_textures = image_font
+        .textures
+        .iter()
+        .map(|handle| {
+            let texture = images
+                .get(handle)
+                .ok_or(ImageFontRenderError::MissingTextureAsset)?;
+            ImageBuffer::<Rgba<u8>, _>::from_raw(
+                texture.width(),
+                texture.height(),
+                texture.data.as_slice(),
+            )
+            .ok_or(ImageFontRenderError::UnknownError)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
     let layout = &image_font.layout;
 
-    let text = image_font.filter_string(&image_font_text.text);
+    let fitted;
+    let image_font_text = match image_font_text.fit {
+        Some(fit) => {
+            fitted = layout::fit_text(image_font, image_font_text, fit)?;
+            &fitted
+        }
+        None => image_font_text,
+    };
+    let text_layout = layout::layout_text(image_font, image_font_text)?;
 
-    if text.is_empty() {
+    if text_layout.glyphs.is_empty() {
         // can't make a 0x0 image, so make a 1x1 transparent black pixel
-        return Ok(Image::new(
+        let layout = ImageFontTextLayout {
+            glyphs: vec![],
+            size: UVec2::ONE,
+        };
+        let image = Image::new(
             Extent3d {
                 width: 1,
                 height: 1,
@@ -207,52 +888,82 @@ pub fn render_text(
             vec![0, 0, 0, 0],
             TextureFormat::Rgba8UnormSrgb,
             RenderAssetUsages::RENDER_WORLD,
-        ));
+        );
+        return Ok((image, layout));
     }
 
-    // as wide as the sum of all characters, as tall as the tallest one
-    let height = text
-        .chars()
-        .map(|c| layout.textures[image_font.index_map[&c]].height())
-        .reduce(f32::max)
-        .unwrap()
-        .ceil() as u32;
-    let width = text
-        .chars()
-        .map(|c| layout.textures[image_font.index_map[&c]].width())
-        .reduce(|a, b| a + b)
-        .unwrap()
-        .ceil() as u32;
-
-    let mut output_image = image::RgbaImage::new(width, height);
-    let font_texture: ImageBuffer<Rgba<u8>, _> = ImageBuffer::from_raw(
-        font_texture.width(),
-        font_texture.height(),
-        font_texture.data.as_slice(),
-    )
-    .ok_or(ImageFontRenderError::UnknownError)?;
-
-    let mut x = 0;
-    for c in text.chars() {
-        let rect = layout.textures[image_font.index_map[&c]];
-        let width = rect.width().ceil() as u32;
-        let height = rect.height().ceil() as u32;
-        output_image.copy_from(
-            &*font_texture.view(rect.min.x as u32, rect.min.y as u32, width, height),
-            x,
-            0,
-        )?;
-        x += width;
-    }
+    // figure out how big the image needs to be to hold all of the glyphs,
+    // leaving room for effects to move them around in
+    let bounds = text_layout.bounds(image_font, image_font_text.effects.padding());
+    let min = bounds.min;
+    let size = bounds.size().as_uvec2();
 
-    if let Some(font_height) = image_font_text.font_height {
-        let width = output_image.width() as f32 * font_height / output_image.height() as f32;
-        output_image = imageops::resize(
-            &output_image,
-            width as u32,
-            font_height as u32,
-            FilterType::Nearest,
+    let mut output_image = image::RgbaImage::new(size.x, size.y);
+
+    let visible_chars = image_font_text.visible_chars.unwrap_or(usize::MAX);
+    let mut glyph_layouts = Vec::with_capacity(text_layout.glyphs.len());
+    for glyph in text_layout.glyphs {
+        let index = glyph.index;
+        let rect = layout.textures[index];
+        let effect = image_font_text.effects.glyph_effect(glyph.cluster);
+        let position = glyph.position + effect.offset - min;
+        let visible = glyph.cluster < visible_chars;
+        glyph_layouts.push(GlyphLayout {
+            key: glyph.key.to_owned(),
+            cluster: glyph.cluster,
+            index,
+            page: image_font.pages[index],
+            source: rect,
+            destination: Rect::from_corners(position.as_vec2(), position.as_vec2() + rect.size()),
+            line: glyph.line,
+            visible,
+        });
+        if !visible {
+            continue;
+        }
+        let view = imageops::crop_imm(
+            &font_textures[image_font.pages[index]],
+            rect.min.x as u32,
+            rect.min.y as u32,
+            rect.width().ceil() as u32,
+            rect.height().ceil() as u32,
         );
+        // overlay rather than copy, since kerning and offsets can make
+        // glyphs overlap
+        if effect.tint == [1.0; 4] {
+            imageops::overlay(
+                &mut output_image,
+                &*view,
+                position.x.into(),
+                position.y.into(),
+            );
+        } else {
+            let tinted = image::RgbaImage::from_fn(view.width(), view.height(), |x, y| {
+                let mut pixel = view.get_pixel(x, y);
+                for (channel, tint) in pixel.0.iter_mut().zip(effect.tint) {
+                    *channel = (*channel as f32 * tint).round() as u8;
+                }
+                pixel
+            });
+            imageops::overlay(
+                &mut output_image,
+                &tinted,
+                position.x.into(),
+                position.y.into(),
+            );
+        }
+    }
+
+    if let Some(scale) = image_font_text.render_scale(image_font) {
+        let size = scaled_size(size, scale);
+        output_image = filter::resize(&output_image, size, image_font_text.filter);
+    }
+
+    // bring the destination rects in line with the resize
+    let scale = UVec2::new(output_image.width(), output_image.height()).as_vec2() / size.as_vec2();
+    for glyph in &mut glyph_layouts {
+        glyph.destination.min *= scale;
+        glyph.destination.max *= scale;
     }
 
     let mut bevy_image = Image::new(
@@ -267,26 +978,66 @@ pub fn render_text(
         TextureFormat::Rgba8UnormSrgb,
         RenderAssetUsages::RENDER_WORLD,
     );
-    bevy_image.sampler = ImageSampler::nearest();
-    Ok(bevy_image)
+    bevy_image.sampler = image_font.sampler.clone();
+    let layout = ImageFontTextLayout {
+        glyphs: glyph_layouts,
+        size: UVec2::new(bevy_image.width(), bevy_image.height()),
+    };
+    Ok((bevy_image, layout))
 }
 
-/// Marks any text where the underlying [`ImageFont`] asset has changed as
-/// dirty, which will cause it to be rerendered.
+/// The size of an image of the given size after scaling it by `scale`.
+/// Rounding both sides (rather than truncating them) keeps the aspect ratio as
+/// close as possible, and integer scales come out exact.
+pub(crate) fn scaled_size(size: UVec2, scale: f32) -> UVec2 {
+    (size.as_vec2() * scale).round().as_uvec2().max(UVec2::ONE)
+}
+
+/// Marks any text where the underlying [`ImageFont`] asset or any of its
+/// textures has changed as dirty, which will cause it to be rerendered. This
+/// is what makes hot-reloading either the font definition or its images update
+/// existing text.
 pub fn mark_changed_fonts_as_dirty(
-    mut events: EventReader<AssetEvent<ImageFont>>,
+    mut font_events: EventReader<AssetEvent<ImageFont>>,
+    mut image_events: EventReader<AssetEvent<Image>>,
+    image_fonts: Res<Assets<ImageFont>>,
     mut query: Query<&mut ImageFontText>,
 ) {
-    let changed_fonts: HashSet<_> = events
+    let mut changed_fonts: HashSet<_> = font_events
         .read()
         .filter_map(|event| match event {
             AssetEvent::Modified { id } | AssetEvent::LoadedWithDependencies { id } => {
                 info!("Image font {id} finished loading; marking as dirty");
-                Some(id)
+                Some(*id)
             }
             _ => None,
         })
         .collect();
+
+    // our own rendered text isn't a font texture, so it's ignored below
+    let changed_images: HashSet<_> = image_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } | AssetEvent::LoadedWithDependencies { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    if !changed_images.is_empty() {
+        for (id, image_font) in image_fonts.iter() {
+            if image_font
+                .textures
+                .iter()
+                .any(|texture| changed_images.contains(&texture.id()))
+            {
+                info!("Texture of image font {id} changed; marking as dirty");
+                changed_fonts.insert(id);
+            }
+        }
+    }
+
+    if changed_fonts.is_empty() {
+        return;
+    }
     for mut image_font_text in &mut query {
         if changed_fonts.contains(&image_font_text.font.id()) {
             image_font_text.set_changed();
@@ -4,19 +4,27 @@ use std::path::PathBuf;
 use bevy::{
     asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadDirectError},
     prelude::*,
-    utils::{BoxedFuture, HashMap},
+    render::{render_asset::RenderAssetUsages, texture::ImageSampler},
+    utils::{BoxedFuture, HashMap, HashSet},
 };
-use serde::{Deserialize, Serialize};
+use ron::extensions::Extensions;
+use serde::{de, Deserialize, Deserializer, Serialize};
 use thiserror::Error;
+use unicode_segmentation::UnicodeSegmentation;
 
-use crate::ImageFont;
+use crate::{bmfont::BmFontParseError, codepage::CodePage, ImageFont, MissingGlyphPolicy};
 
 /// Human-readable way to specify where the characters in an image font are.
 #[derive(Serialize, Deserialize)]
 pub enum ImageFontLayout {
     /// Interprets the string as a "grid" and slices up the input image
     /// accordingly. Leading and trailing newlines are stripped, but spaces
-    /// are not (since your font might use them as padding).
+    /// are not (since your font might use them as padding). The image's width
+    /// and height must be exact multiples of the number of columns and rows,
+    /// and every character other than space can only appear once. Each cell
+    /// holds a grapheme cluster, so a letter followed by a combining accent
+    /// takes up a single cell. (This means combining marks can't get cells of
+    /// their own; use [`ImageFontLayout::Grid`] for those.)
     ///
     /// ```rust
     /// # use extol_image_font::loader::*;
@@ -56,84 +64,311 @@ pub enum ImageFontLayout {
     /// ```
     ManualMonospace {
         size: UVec2,
-        coords: HashMap<char, UVec2>,
+        #[serde(with = "glyph_entries")]
+        coords: Vec<(String, UVec2)>,
     },
 
     /// Fully specifies the bounds of each character. The most general case.
     ///
+    /// Here and in [`ImageFontLayout::ManualMonospace`], the keys can be
+    /// strings instead of characters, which lets you define glyphs for
+    /// grapheme clusters made up of several codepoints, such as flags, and
+    /// ligatures such as `"->"` or `"fi"`. When rendering, the longest
+    /// sequence that the font has a glyph for wins. Each key can only appear
+    /// once, however it's written (`'a'` and `"a"` are the same key).
+    ///
     /// ```rust
     /// # use extol_image_font::loader::*;
     /// let s = r#"
     /// Manual({
     /// 'a': URect(min: (0, 0), max: (10, 20)),
-    /// 'b': URect(min: (20, 20), max: (25, 25))
+    /// 'b': URect(min: (20, 20), max: (25, 25)),
+    /// "🇯🇵": URect(min: (30, 0), max: (40, 10)),
+    /// "->": URect(min: (40, 0), max: (50, 10)),
     /// })
     /// "#;
     /// ron::from_str::<ImageFontLayout>(s).unwrap();
     /// ```
-    Manual(HashMap<char, URect>),
+    Manual(#[serde(with = "glyph_entries")] Vec<(String, URect)>),
+
+    /// A uniform grid of `cell_size` cells, `columns` to a row, filled in
+    /// reading order with consecutive codepoints. If `columns` is left out,
+    /// the grid spans the width of the image.
+    ///
+    /// ```rust
+    /// # use extol_image_font::loader::*;
+    /// // 8x8 cells, 16 per row, starting at the space character
+    /// let s = r#"
+    /// Grid(cell_size: (8, 8), columns: Some(16), codepoints: Start(' '))
+    /// "#;
+    /// ron::from_str::<ImageFontLayout>(s).unwrap();
+    ///
+    /// // digits followed by uppercase Latin, then Greek
+    /// let s = r#"
+    /// Grid(
+    ///   cell_size: (8, 8),
+    ///   codepoints: Ranges([('0', '9'), ('A', 'Z'), ('\u{391}', '\u{3A9}')]),
+    /// )
+    /// "#;
+    /// ron::from_str::<ImageFontLayout>(s).unwrap();
+    /// ```
+    Grid {
+        cell_size: UVec2,
+        #[serde(default)]
+        columns: Option<u32>,
+        codepoints: GridCodepoints,
+    },
+
+    /// A 16x16 grid holding the 256 characters of a code page in order, as
+    /// used by most DOS and roguelike font sheets. Shorthand for a
+    /// [`Grid`](ImageFontLayout::Grid) with [`GridCodepoints::CodePage`]
+    /// whose cell size is a sixteenth of the image. If a character appears
+    /// more than once in the code page, the first cell is used.
+    ///
+    /// ```rust
+    /// # use extol_image_font::loader::*;
+    /// ron::from_str::<ImageFontLayout>("CodePage(Cp437)").unwrap();
+    /// ```
+    CodePage(CodePage),
+}
+
+/// Which codepoints the cells of an [`ImageFontLayout::Grid`] hold.
+#[derive(Serialize, Deserialize)]
+pub enum GridCodepoints {
+    /// Every cell in the image, starting with the given character.
+    Start(char),
+    /// Each of the inclusive ranges in turn. Surrogates are skipped.
+    Ranges(Vec<(char, char)>),
+    /// The 256 characters of a code page, in order. If a character appears
+    /// more than once in the code page, the first cell is used.
+    CodePage(CodePage),
 }
 
 impl ImageFontLayout {
-    /// Given the image size, returns a map from each codepoint to its location.
-    fn into_char_map(self, size: UVec2) -> HashMap<char, Rect> {
-        match self {
+    /// Given the image size, returns a map from each glyph's key to its
+    /// location.
+    ///
+    /// Problems with the layout, such as characters that appear twice or
+    /// rects that don't fit in the image, are errors unless `lenient` is set,
+    /// in which case they're logged and worked around as best as possible.
+    #[allow(clippy::result_large_err)]
+    fn into_char_map(
+        self,
+        size: UVec2,
+        lenient: bool,
+    ) -> Result<HashMap<String, Rect>, ImageFontLoadError> {
+        let rect_map = match self {
             ImageFontLayout::Automatic(str) => {
                 // trim() removes whitespace, which is not what we want!
                 let str = str.trim_start_matches('\n').trim_end_matches('\n');
-                let mut rect_map = HashMap::new();
                 let max_chars_per_line = str
                     .lines()
                     // important: *not* l.len()
-                    .map(|l| l.chars().count())
+                    .map(|l| l.graphemes(true).count())
                     .max()
-                    .expect("can't create character map from an empty string")
-                    as u32;
+                    .unwrap_or(0) as u32;
+                if max_chars_per_line == 0 {
+                    check(lenient, ImageFontLoadError::EmptyLayout)?;
+                    return Ok(HashMap::new());
+                }
 
                 if size.x % max_chars_per_line != 0 {
-                    warn!(
-                        "image width {} is not an exact multiple of character count {}",
-                        size.x, max_chars_per_line
-                    );
+                    check(
+                        lenient,
+                        ImageFontLoadError::WidthNotDivisible {
+                            width: size.x,
+                            columns: max_chars_per_line,
+                        },
+                    )?;
                 }
                 let line_count = str.lines().count() as u32;
                 if size.y % line_count != 0 {
-                    warn!(
-                        "image height {} is not an exact multiple of character count {}",
-                        size.y, line_count
-                    );
+                    check(
+                        lenient,
+                        ImageFontLoadError::HeightNotDivisible {
+                            height: size.y,
+                            rows: line_count,
+                        },
+                    )?;
                 }
 
-                let rect_width = (size.x / max_chars_per_line) as f32;
-                let rect_height = (size.y / line_count) as f32;
-
-                for (row, line) in str.lines().enumerate() {
-                    for (col, char) in line.chars().enumerate() {
-                        let rect = Rect::new(
-                            rect_width * col as f32,
-                            rect_height * row as f32,
-                            rect_width * (col + 1) as f32,
-                            rect_height * (row + 1) as f32,
-                        );
-                        rect_map.insert(char, rect);
+                let cell_size = size / UVec2::new(max_chars_per_line, line_count);
+                let cells = str.lines().enumerate().flat_map(|(row, line)| {
+                    line.graphemes(true)
+                        .enumerate()
+                        .map(move |(col, key)| (row, col, key.to_owned()))
+                });
+                grid_rects(cell_size, cells, lenient)?
+            }
+            ImageFontLayout::Grid {
+                cell_size,
+                columns,
+                codepoints,
+            } => {
+                if cell_size.cmpeq(UVec2::ZERO).any() || columns == Some(0) {
+                    check(lenient, ImageFontLoadError::EmptyLayout)?;
+                    return Ok(HashMap::new());
+                }
+                let columns = columns.unwrap_or(size.x / cell_size.x).max(1) as usize;
+                let chars: Vec<(usize, char)> = match codepoints {
+                    // fill the whole image
+                    GridCodepoints::Start(start) => {
+                        let cell_count = columns * (size.y / cell_size.y) as usize;
+                        (start..=char::MAX).take(cell_count).enumerate().collect()
                     }
+                    GridCodepoints::Ranges(ranges) => ranges
+                        .into_iter()
+                        .flat_map(|(first, last)| first..=last)
+                        .enumerate()
+                        .collect(),
+                    GridCodepoints::CodePage(code_page) => code_page_cells(code_page).collect(),
+                };
+                let cells = chars
+                    .into_iter()
+                    .map(|(i, c)| (i / columns, i % columns, c.to_string()));
+                grid_rects(cell_size, cells, lenient)?
+            }
+            ImageFontLayout::CodePage(code_page) => {
+                if size.x % 16 != 0 {
+                    check(
+                        lenient,
+                        ImageFontLoadError::WidthNotDivisible {
+                            width: size.x,
+                            columns: 16,
+                        },
+                    )?;
                 }
-                rect_map
+                if size.y % 16 != 0 {
+                    check(
+                        lenient,
+                        ImageFontLoadError::HeightNotDivisible {
+                            height: size.y,
+                            rows: 16,
+                        },
+                    )?;
+                }
+                let cells =
+                    code_page_cells(code_page).map(|(i, c)| (i / 16, i % 16, c.to_string()));
+                grid_rects(size / 16, cells, lenient)?
             }
-            ImageFontLayout::ManualMonospace { size, coords } => coords
-                .into_iter()
-                .map(|(c, top_left)| {
-                    (
-                        c,
-                        Rect::from_corners(top_left.as_vec2(), (size + top_left).as_vec2()),
-                    )
-                })
-                .collect(),
-            ImageFontLayout::Manual(urect_map) => urect_map
-                .into_iter()
-                .map(|(k, v)| (k, v.as_rect()))
-                .collect(),
+            ImageFontLayout::ManualMonospace {
+                size: glyph_size,
+                coords,
+            } => {
+                let mut rects = vec![];
+                for (key, top_left) in coords {
+                    let bottom_right = top_left
+                        .x
+                        .checked_add(glyph_size.x)
+                        .zip(top_left.y.checked_add(glyph_size.y));
+                    match bottom_right {
+                        Some((right, bottom)) => rects.push((
+                            key,
+                            URect::from_corners(top_left, UVec2::new(right, bottom)),
+                        )),
+                        None => check(
+                            lenient,
+                            ImageFontLoadError::RectOverflow {
+                                key,
+                                top_left,
+                                size: glyph_size,
+                            },
+                        )?,
+                    }
+                }
+                manual_rects(rects, lenient)?
+            }
+            ImageFontLayout::Manual(rects) => manual_rects(rects, lenient)?,
+        };
+
+        let mut keys: Vec<String> = rect_map.keys().cloned().collect();
+        keys.sort();
+        let mut char_map = HashMap::new();
+        for key in keys {
+            let rect = rect_map[&key];
+            if rect.min.cmpgt(rect.max).any() || rect.max.cmpgt(size).any() {
+                check(
+                    lenient,
+                    ImageFontLoadError::RectOutOfBounds {
+                        key: key.clone(),
+                        rect,
+                        size,
+                    },
+                )?;
+                continue;
+            }
+            char_map.insert(key, rect.as_rect());
+        }
+        Ok(char_map)
+    }
+}
+
+/// Computes the rects for a grid of `cell_size` cells, given the row and
+/// column of each glyph.
+#[allow(clippy::result_large_err)]
+fn grid_rects(
+    cell_size: UVec2,
+    cells: impl IntoIterator<Item = (usize, usize, String)>,
+    lenient: bool,
+) -> Result<HashMap<String, URect>, ImageFontLoadError> {
+    let mut rect_map = HashMap::new();
+    let mut positions = HashMap::new();
+    for (row, col, key) in cells {
+        // spaces are allowed to repeat, since they're used as padding
+        if let Some(&first) = positions.get(&key).filter(|_| key != " ") {
+            check(
+                lenient,
+                ImageFontLoadError::DuplicateGlyph {
+                    key: key.clone(),
+                    first,
+                    second: (row, col),
+                },
+            )?;
+        }
+        positions.insert(key.clone(), (row, col));
+        let top_left = cell_size * UVec2::new(col as u32, row as u32);
+        rect_map.insert(key, URect::from_corners(top_left, top_left + cell_size));
+    }
+    Ok(rect_map)
+}
+
+/// Collects the rects of a manual layout, checking that no key appears twice.
+#[allow(clippy::result_large_err)]
+fn manual_rects(
+    rects: Vec<(String, URect)>,
+    lenient: bool,
+) -> Result<HashMap<String, URect>, ImageFontLoadError> {
+    let mut rect_map = HashMap::new();
+    for (key, rect) in rects {
+        if rect_map.contains_key(&key) {
+            check(
+                lenient,
+                ImageFontLoadError::DuplicateEntry { key: key.clone() },
+            )?;
         }
+        rect_map.insert(key, rect);
+    }
+    Ok(rect_map)
+}
+
+/// The characters of the code page along with their index, skipping any that
+/// already appeared earlier in it.
+fn code_page_cells(code_page: CodePage) -> impl Iterator<Item = (usize, char)> {
+    let mut seen = HashSet::new();
+    code_page
+        .chars()
+        .enumerate()
+        .filter(move |&(_, c)| seen.insert(c))
+}
+
+/// Returns `error` unless `lenient` is set, in which case it's logged instead.
+#[allow(clippy::result_large_err)]
+pub(crate) fn check(lenient: bool, error: ImageFontLoadError) -> Result<(), ImageFontLoadError> {
+    if lenient {
+        warn!("{error}");
+        Ok(())
+    } else {
+        Err(error)
     }
 }
 
@@ -141,13 +376,366 @@ impl ImageFontLayout {
 /// to write these. See the docs for [`ImageFontLayout`]'s variants for
 /// information on how to write the syntax, or [the example font's RON asset].
 ///
+/// A font with a single texture gives its `image` and `layout` directly. A
+/// font that's spread across several textures lists them in `pages` instead:
+///
+/// ```rust
+/// # use extol_image_font::loader::*;
+/// let s = r#"
+/// (
+///   pages: [
+///     (image: "latin.png", layout: Automatic("abc")),
+///     (image: "greek.png", layout: Automatic("αβγ")),
+///   ]
+/// )
+/// "#;
+/// ron::from_str::<ImageFontSettings>(s).unwrap();
+/// ```
+///
+/// Both can be combined, in which case `image` and `layout` make up the first
+/// page. The loader parses the file with RON's `implicit_some` extension
+/// enabled, so `image` and `layout` don't need to be wrapped in `Some`.
+///
+/// Characters that the font doesn't draw can be rendered using the glyph of
+/// another character, either by listing them in `aliases` or, for fonts that
+/// only have one case, by setting `fold_case`:
+///
+/// ```rust
+/// # use extol_image_font::loader::*;
+/// let s = r#"
+/// (
+///   pages: [(image: "font.png", layout: Automatic("ABC-\""))],
+///   aliases: { '—': '-', '“': '"', '”': '"' },
+///   fold_case: true,
+/// )
+/// "#;
+/// ron::from_str::<ImageFontSettings>(s).unwrap();
+/// ```
+///
 /// [the example font's RON asset](https://github.com/deifactor/extol_image_font/blob/main/assets/example_font.image_font.ron)
 #[derive(Serialize, Deserialize)]
 pub struct ImageFontSettings {
+    #[serde(default)]
+    pub image: Option<PathBuf>,
+    #[serde(default)]
+    pub layout: Option<ImageFontLayout>,
+    #[serde(default)]
+    pub pages: Vec<ImageFontPageSettings>,
+    /// Maps characters to the character whose glyph they should be drawn
+    /// with. Aliases can point to other aliases. An alias takes precedence
+    /// over a glyph defined in the layout. Like the keys of manual layouts,
+    /// both sides can be strings, and each character can only have one
+    /// alias.
+    #[serde(default, with = "glyph_aliases")]
+    pub aliases: Vec<(String, String)>,
+    /// If set, characters that aren't in the font are drawn with the glyph
+    /// of their uppercase or lowercase counterpart, if there is one.
+    #[serde(default)]
+    pub fold_case: bool,
+    /// When text contains a character the font has no glyph for, but the font
+    /// has glyphs for its base letter and combining marks (after Unicode
+    /// decomposition), the marks are drawn on top of the base letter. By
+    /// default, marks are drawn at the same position as the letter, which
+    /// works if the accent is drawn in the right place within its cell; this
+    /// moves the given marks by an additional offset, in pixels.
+    ///
+    /// ```rust
+    /// # use extol_image_font::loader::*;
+    /// let s = r#"
+    /// (
+    ///   pages: [
+    ///     (image: "letters.png", layout: Automatic("aeiou")),
+    ///     // combining grave, acute, circumflex and tilde
+    ///     (image: "marks.png", layout: Grid(
+    ///       cell_size: (6, 12),
+    ///       codepoints: Ranges([('\u{300}', '\u{303}')]),
+    ///     )),
+    ///   ],
+    ///   // move the tilde up a pixel
+    ///   mark_offsets: { '\u{303}': (0, -1) },
+    /// )
+    /// "#;
+    /// ron::from_str::<ImageFontSettings>(s).unwrap();
+    /// ```
+    #[serde(default)]
+    pub mark_offsets: HashMap<char, IVec2>,
+}
+
+/// A single texture of an image font and where the characters are in it.
+#[derive(Serialize, Deserialize)]
+pub struct ImageFontPageSettings {
     pub image: PathBuf,
     pub layout: ImageFontLayout,
 }
 
+impl ImageFontSettings {
+    /// All the pages in the font, including the one given by `image` and
+    /// `layout`.
+    #[allow(clippy::result_large_err)]
+    fn into_pages(self) -> Result<Vec<ImageFontPageSettings>, ImageFontLoadError> {
+        let mut pages = vec![];
+        match (self.image, self.layout) {
+            (Some(image), Some(layout)) => pages.push(ImageFontPageSettings { image, layout }),
+            (None, None) => {}
+            _ => return Err(ImageFontLoadError::ImageWithoutLayout),
+        }
+        pages.extend(self.pages);
+        if pages.is_empty() {
+            return Err(ImageFontLoadError::NoPages);
+        }
+        Ok(pages)
+    }
+}
+
+/// Adds the aliased characters and, if `fold_case` is set, the other case of
+/// every character to the index map, so that rendering never has to look at
+/// aliases.
+#[allow(clippy::result_large_err)]
+fn resolve_aliases(
+    index_map: &mut HashMap<String, usize>,
+    alias_list: Vec<(String, String)>,
+    fold_case: bool,
+    lenient: bool,
+) -> Result<(), ImageFontLoadError> {
+    let mut aliases = HashMap::new();
+    for (from, to) in alias_list {
+        if aliases.contains_key(&from) {
+            check(
+                lenient,
+                ImageFontLoadError::DuplicateAlias { key: from.clone() },
+            )?;
+        }
+        aliases.insert(from, to);
+    }
+    // folding before resolving aliases lets them point at either case, and
+    // folding afterwards gives aliases both cases as well
+    if fold_case {
+        add_other_cases(index_map);
+    }
+    let glyphs = index_map.clone();
+    let mut sorted: Vec<_> = aliases.iter().collect();
+    sorted.sort();
+    for (from, to) in sorted {
+        // follow chains of aliases; a chain longer than the number of aliases
+        // must be a cycle
+        let mut target = to;
+        let mut index = None;
+        for _ in 0..=aliases.len() {
+            match (glyphs.get(target), aliases.get(target)) {
+                (Some(&i), _) => {
+                    index = Some(i);
+                    break;
+                }
+                (None, Some(next)) => target = next,
+                (None, None) => break,
+            }
+        }
+        match index {
+            Some(index) => {
+                index_map.insert(from.clone(), index);
+            }
+            None => check(
+                lenient,
+                ImageFontLoadError::UnresolvedAlias {
+                    from: from.clone(),
+                    to: to.clone(),
+                },
+            )?,
+        }
+    }
+    if fold_case {
+        add_other_cases(index_map);
+    }
+    Ok(())
+}
+
+/// Maps the uppercase and lowercase versions of every glyph's key to its
+/// glyph, unless they already have one.
+fn add_other_cases(index_map: &mut HashMap<String, usize>) {
+    let mut defined: Vec<(String, usize)> =
+        index_map.iter().map(|(key, &i)| (key.clone(), i)).collect();
+    defined.sort();
+    for (key, index) in defined {
+        for other in [key.to_lowercase(), key.to_uppercase()] {
+            // characters like 'ß' uppercase to more than one character, and
+            // a glyph for "SS" would be picked over two 'S' glyphs
+            if other.chars().count() == key.chars().count() {
+                index_map.entry(other).or_insert(index);
+            }
+        }
+    }
+}
+
+/// A glyph key, which can be written as either a character or a string.
+struct GlyphKey(String);
+
+impl<'de> Deserialize<'de> for GlyphKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = GlyphKey;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a character or a string")
+            }
+
+            fn visit_char<E: de::Error>(self, c: char) -> Result<GlyphKey, E> {
+                Ok(GlyphKey(c.to_string()))
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<GlyphKey, E> {
+                Ok(GlyphKey(s.to_owned()))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// (De)serializes a map whose keys are [`GlyphKey`]s as a list of entries,
+/// so that keys that appear more than once can be reported instead of
+/// silently overwriting each other.
+mod glyph_entries {
+    use std::marker::PhantomData;
+
+    use serde::{de, ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::GlyphKey;
+
+    pub fn serialize<S, V>(entries: &[(String, V)], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        V: Serialize,
+    {
+        let mut map = serializer.serialize_map(Some(entries.len()))?;
+        for (key, value) in entries {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+
+    pub fn deserialize<'de, D, V>(deserializer: D) -> Result<Vec<(String, V)>, D::Error>
+    where
+        D: Deserializer<'de>,
+        V: Deserialize<'de>,
+    {
+        struct Visitor<V>(PhantomData<V>);
+
+        impl<'de, V: Deserialize<'de>> de::Visitor<'de> for Visitor<V> {
+            type Value = Vec<(String, V)>;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a map")
+            }
+
+            fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut entries = vec![];
+                while let Some((GlyphKey(key), value)) = map.next_entry()? {
+                    entries.push((key, value));
+                }
+                Ok(entries)
+            }
+        }
+
+        deserializer.deserialize_map(Visitor(PhantomData))
+    }
+}
+
+/// (De)serializes a map whose keys and values are both [`GlyphKey`]s as a
+/// list of entries.
+mod glyph_aliases {
+    use serde::{Deserializer, Serializer};
+
+    use super::{glyph_entries, GlyphKey};
+
+    pub fn serialize<S: Serializer>(
+        entries: &[(String, String)],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        glyph_entries::serialize(entries, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<(String, String)>, D::Error> {
+        let entries = glyph_entries::deserialize::<D, GlyphKey>(deserializer)?;
+        Ok(entries
+            .into_iter()
+            .map(|(key, GlyphKey(value))| (key, value))
+            .collect())
+    }
+}
+
+/// Label of the labeled asset containing the texture for the given page.
+pub(crate) fn page_label(page: usize) -> String {
+    format!("page{page}")
+}
+
+/// Settings for loading an image font that aren't part of the font itself.
+/// These are shared by all of this crate's loaders and can be set per asset
+/// using a `.meta` file, like
+///
+/// ```rust
+/// # use extol_image_font::loader::*;
+/// let s = r#"
+/// (
+///     sampler: Default,
+///     font_height: Some(24.0),
+///     missing_glyph: Replace('?'),
+/// )
+/// "#;
+/// ron::from_str::<ImageFontLoaderSettings>(s).unwrap();
+/// ```
+///
+/// Any field that's left out takes its default value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageFontLoaderSettings {
+    /// Sampler used for the font's textures and for images rendered with the
+    /// font. Defaults to nearest-neighbor sampling, since anything else
+    /// blurs pixel art.
+    pub sampler: ImageSampler,
+    /// Height to render text at if [`ImageFontText::font_height`] isn't set.
+    ///
+    /// [`ImageFontText::font_height`]: crate::ImageFontText::font_height
+    pub font_height: Option<f32>,
+    /// What to do with characters that the font has no glyph for.
+    pub missing_glyph: MissingGlyphPolicy,
+    /// Which worlds the font's textures are kept in. Rendering text reads the
+    /// textures on the CPU, so `MAIN_WORLD` is required for that; drop
+    /// `RENDER_WORLD` to avoid uploading textures you never draw directly.
+    pub texture_usage: RenderAssetUsages,
+    /// If set, mistakes in a `.image_font.ron` file, such as characters that
+    /// appear twice (in one page or across pages), rects that lie outside the
+    /// image or aliases that lead nowhere, are logged as warnings instead of
+    /// failing the load. The same goes for glyphs that are out of bounds or
+    /// appear twice in a BMFont descriptor. Characters whose rects are out of
+    /// bounds and broken aliases are dropped, and for duplicated characters
+    /// the last one wins.
+    pub lenient: bool,
+}
+
+impl Default for ImageFontLoaderSettings {
+    fn default() -> Self {
+        Self {
+            sampler: ImageSampler::nearest(),
+            font_height: None,
+            missing_glyph: MissingGlyphPolicy::default(),
+            texture_usage: RenderAssetUsages::default(),
+            lenient: false,
+        }
+    }
+}
+
+impl ImageFontLoaderSettings {
+    /// Applies the settings that concern one of the font's textures.
+    pub(crate) fn apply_to_texture(&self, texture: &mut Image) {
+        texture.sampler = self.sampler.clone();
+        texture.asset_usage = self.texture_usage;
+    }
+}
+
 /// Loader for [`ImageFont`]s.
 #[derive(Debug, Default)]
 pub struct ImageFontLoader;
@@ -164,46 +752,122 @@ pub enum ImageFontLoadError {
     LoadDirect(#[from] LoadDirectError),
     #[error("path at {0} wasn't loaded as an image")]
     NotAnImage(PathBuf),
+    #[error("`image` and `layout` must either both be present or both be absent")]
+    ImageWithoutLayout,
+    #[error("font has no pages")]
+    NoPages,
+    #[error("layout is empty")]
+    EmptyLayout,
+    #[error("image width {width} is not a multiple of the {columns} columns in the layout")]
+    WidthNotDivisible { width: u32, columns: u32 },
+    #[error("image height {height} is not a multiple of the {rows} rows in the layout")]
+    HeightNotDivisible { height: u32, rows: u32 },
+    /// A glyph appears twice in a grid layout such as
+    /// [`ImageFontLayout::Automatic`]. Positions are given as zero-based
+    /// `(row, column)`.
+    #[error(
+        "{key:?} appears at both row {}, column {} and row {}, column {} (counting from 0)",
+        first.0, first.1, second.0, second.1
+    )]
+    DuplicateGlyph {
+        key: String,
+        first: (usize, usize),
+        second: (usize, usize),
+    },
+    /// A glyph appears twice in a manual layout, such as
+    /// [`ImageFontLayout::Manual`], or in a BMFont descriptor.
+    #[error("{key:?} appears more than once in the layout")]
+    DuplicateEntry { key: String },
+    /// A glyph appears on two different pages.
+    #[error("{key:?} appears on both page {first_page} and page {second_page}")]
+    DuplicatePageGlyph {
+        key: String,
+        first_page: usize,
+        second_page: usize,
+    },
+    #[error("{key:?} has more than one alias")]
+    DuplicateAlias { key: String },
+    #[error(
+        "rect for {key:?} at {top_left} with size {size} goes past the largest possible image"
+    )]
+    RectOverflow {
+        key: String,
+        top_left: UVec2,
+        size: UVec2,
+    },
+    #[error("alias from {from:?} to {to:?} doesn't lead to a character in the font")]
+    UnresolvedAlias { from: String, to: String },
+    #[error("rect {rect:?} for {key:?} doesn't fit in the {size} image")]
+    RectOutOfBounds {
+        key: String,
+        rect: URect,
+        size: UVec2,
+    },
+    #[error(
+        "glyph for {key:?} is {size}, which is bigger than the largest texture page \
+         ({max}x{max})",
+        max = crate::packing::MAX_PAGE_SIZE
+    )]
+    GlyphTooLarge { key: String, size: UVec2 },
+    #[error("couldn't parse BMFont descriptor: {0}")]
+    BmFont(#[from] BmFontParseError),
+    #[error("couldn't parse baked font: {0}")]
+    Baked(#[from] crate::baked::BakedImageFontParseError),
+    #[cfg(feature = "bdf")]
+    #[error("couldn't parse BDF font: {0}")]
+    Bdf(#[from] crate::bdf::BdfParseError),
 }
 
 impl AssetLoader for ImageFontLoader {
     type Asset = ImageFont;
 
-    // We could use ImageFontSettings, but an AssetLoader's settings has to
-    // imnplement `Default`, and there's no sensible default value for that
-    // type.
-    type Settings = ();
+    type Settings = ImageFontLoaderSettings;
 
     type Error = ImageFontLoadError;
 
     fn load<'a>(
         &'a self,
         reader: &'a mut Reader,
-        _settings: &'a Self::Settings,
+        settings: &'a Self::Settings,
         load_context: &'a mut LoadContext,
     ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
         Box::pin(async move {
             let mut str = String::new();
             reader.read_to_string(&mut str).await?;
-            let disk_format: ImageFontSettings = ron::from_str(&str)?;
+            let mut disk_format: ImageFontSettings = ron::Options::default()
+                .with_default_extension(Extensions::IMPLICIT_SOME)
+                .from_str(&str)?;
 
-            // need the image loaded immediately because we need its size
-            let image_path = load_context
+            let aliases = std::mem::take(&mut disk_format.aliases);
+            let fold_case = disk_format.fold_case;
+            let mark_offsets = std::mem::take(&mut disk_format.mark_offsets);
+            let parent = load_context
                 .path()
                 .parent()
                 .expect("asset's parent is None?")
-                .join(disk_format.image.clone());
-            let image = load_context
-                .load_direct(image_path.clone())
-                .await?
-                .take::<Image>()
-                .ok_or(ImageFontLoadError::NotAnImage(image_path))?;
-
-            let size = image.size();
-            let char_map = disk_format.layout.into_char_map(size);
-            let image_handle = load_context.add_labeled_asset("texture".into(), image);
-
-            Ok(ImageFont::from_char_map(image_handle, size, &char_map))
+                .to_owned();
+            let mut pages = vec![];
+            for (i, page) in disk_format.into_pages()?.into_iter().enumerate() {
+                // need the image loaded immediately because we need its size
+                let image_path = parent.join(page.image);
+                let mut image = load_context
+                    .load_direct(image_path.clone())
+                    .await?
+                    .take::<Image>()
+                    .ok_or(ImageFontLoadError::NotAnImage(image_path))?;
+                settings.apply_to_texture(&mut image);
+
+                let size = image.size();
+                let char_map = page.layout.into_char_map(size, settings.lenient)?;
+                let image_handle = load_context.add_labeled_asset(page_label(i), image);
+                pages.push((image_handle, size, char_map));
+            }
+
+            let mut font = ImageFont::from_char_maps(pages, settings)?;
+            font.mark_offsets = mark_offsets;
+            resolve_aliases(&mut font.index_map, aliases, fold_case, settings.lenient)?;
+            font.add_normalized_keys();
+            Ok(font)
         })
     }
 
@@ -211,3 +875,35 @@ impl AssetLoader for ImageFontLoader {
         &["image_font.ron"]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GlyphMetrics;
+
+    #[test]
+    fn folding_case_keeps_the_number_of_characters() {
+        let keys = ["P", "A", "S", "ß"];
+        let mut layout = TextureAtlasLayout::new_empty(Vec2::new(keys.len() as f32, 1.0));
+        let mut index_map = HashMap::new();
+        let mut metrics = vec![];
+        for (i, key) in keys.into_iter().enumerate() {
+            let rect = Rect::new(i as f32, 0.0, i as f32 + 1.0, 1.0);
+            index_map.insert(key.to_owned(), layout.add_texture(rect));
+            metrics.push(GlyphMetrics::for_rect(rect));
+        }
+        resolve_aliases(&mut index_map, vec![], true, false).unwrap();
+        assert!(!index_map.contains_key("SS"));
+        assert_eq!(index_map["s"], index_map["S"]);
+
+        let pages = vec![0; metrics.len()];
+        let font = ImageFont::new(layout, vec![Handle::default()], pages, index_map, metrics);
+        let glyphs: Vec<&str> = font
+            .glyphs("PASS")
+            .unwrap()
+            .iter()
+            .map(|glyph| glyph.key)
+            .collect();
+        assert_eq!(glyphs, ["P", "A", "S", "S"]);
+    }
+}
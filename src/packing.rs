@@ -0,0 +1,63 @@
+//! Packing glyphs into generated atlas textures.
+use bevy::prelude::*;
+
+/// Largest texture we'll generate. This is the most WebGL2 is guaranteed to
+/// support.
+pub(crate) const MAX_PAGE_SIZE: u32 = 2048;
+
+/// The result of [`pack`].
+pub(crate) struct Packing {
+    /// Size of each page.
+    pub page_sizes: Vec<UVec2>,
+    /// For each packed rectangle, which page it's on and the position of its
+    /// top-left corner on that page.
+    pub positions: Vec<(usize, UVec2)>,
+}
+
+/// Packs rectangles of the given sizes into as few pages as possible using a
+/// simple shelf packer. If a rectangle doesn't fit within [`MAX_PAGE_SIZE`],
+/// returns its index instead.
+pub(crate) fn pack(sizes: &[UVec2]) -> Result<Packing, usize> {
+    if let Some(too_large) = sizes
+        .iter()
+        .position(|size| size.cmpgt(UVec2::splat(MAX_PAGE_SIZE)).any())
+    {
+        return Err(too_large);
+    }
+    let area: u64 = sizes.iter().map(|size| u64::from(size.x * size.y)).sum();
+    let widest = sizes.iter().map(|size| size.x).max().unwrap_or(0);
+    // aim for a roughly square atlas
+    let width = ((area as f64).sqrt().ceil() as u32)
+        .max(widest)
+        .max(1)
+        .next_power_of_two()
+        .min(MAX_PAGE_SIZE);
+
+    // placing the tallest rects first keeps the shelves tight
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(sizes[i].y));
+
+    let mut page_sizes = vec![];
+    let mut positions = vec![(0, UVec2::ZERO); sizes.len()];
+    let mut cursor = UVec2::ZERO;
+    let mut shelf_height = 0;
+    for i in order {
+        let size = sizes[i];
+        if cursor.x + size.x > width {
+            cursor = UVec2::new(0, cursor.y + shelf_height);
+            shelf_height = 0;
+        }
+        if cursor.y + size.y > MAX_PAGE_SIZE {
+            page_sizes.push(UVec2::new(width, cursor.y));
+            cursor = UVec2::ZERO;
+        }
+        positions[i] = (page_sizes.len(), cursor);
+        cursor.x += size.x;
+        shelf_height = shelf_height.max(size.y);
+    }
+    page_sizes.push(UVec2::new(width, (cursor.y + shelf_height).max(1)));
+    Ok(Packing {
+        page_sizes,
+        positions,
+    })
+}
@@ -0,0 +1,160 @@
+//! Revealing text one character at a time, as in the dialogue boxes of most
+//! RPGs.
+use bevy::{prelude::*, utils::HashMap};
+use derive_setters::Setters;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{ImageFontText, TextNormalization};
+
+/// Add this to an entity with an [`ImageFontText`] to reveal its text one
+/// grapheme cluster at a time, by advancing
+/// [`ImageFontText::visible_chars`]. A [`CharacterRevealed`] event is sent
+/// for each one, which is handy for playing 'blip' sounds.
+///
+/// Since the whole text is laid out up front, words don't jump from one line
+/// to the next as they're revealed.
+///
+/// Call [`Self::restart`] after changing the text to reveal the new text from
+/// the start. To show all of the text right away, set
+/// [`ImageFontText::visible_chars`] to `None` and remove this component.
+///
+/// ```rust
+/// # use extol_image_font::reveal::TypewriterReveal;
+/// let reveal = TypewriterReveal::default()
+///     .seconds_per_char(0.03)
+///     .pause('—', 0.5);
+/// ```
+#[derive(Debug, Clone, Reflect, Component, Setters)]
+#[setters(into)]
+pub struct TypewriterReveal {
+    /// How long to wait between revealing one grapheme cluster and the next.
+    pub seconds_per_char: f32,
+    /// How much longer to wait after revealing a grapheme cluster that ends
+    /// in one of these characters. By default there's a short pause after
+    /// commas and similar, and a longer one after the end of a sentence.
+    #[setters(skip)]
+    pub pauses: HashMap<char, f32>,
+    /// How many grapheme clusters have been revealed so far.
+    #[setters(skip)]
+    pub revealed: usize,
+    /// Time left until the next grapheme cluster is revealed.
+    #[setters(skip)]
+    pub cooldown: f32,
+    /// The grapheme clusters of the text, so that they're only worked out
+    /// again when the text changes.
+    #[reflect(ignore)]
+    #[setters(skip)]
+    clusters: Option<ClusterCache>,
+}
+
+/// The grapheme clusters of some text, after normalization.
+#[derive(Debug, Clone)]
+struct ClusterCache {
+    text: String,
+    normalization: TextNormalization,
+    clusters: Vec<String>,
+}
+
+impl ClusterCache {
+    fn new(image_font_text: &ImageFontText) -> Self {
+        let normalized = image_font_text.normalization.apply(&image_font_text.text);
+        Self {
+            text: image_font_text.text.clone(),
+            normalization: image_font_text.normalization,
+            clusters: normalized.graphemes(true).map(str::to_owned).collect(),
+        }
+    }
+
+    fn is_for(&self, image_font_text: &ImageFontText) -> bool {
+        self.normalization == image_font_text.normalization && self.text == image_font_text.text
+    }
+}
+
+impl Default for TypewriterReveal {
+    fn default() -> Self {
+        let short = [',', ';', ':'].map(|c| (c, 0.15));
+        let long = ['.', '!', '?', '…'].map(|c| (c, 0.4));
+        Self {
+            seconds_per_char: 0.05,
+            pauses: short.into_iter().chain(long).collect(),
+            revealed: 0,
+            cooldown: 0.0,
+            clusters: None,
+        }
+    }
+}
+
+impl TypewriterReveal {
+    /// Sets the extra time to wait after revealing `c`.
+    pub fn pause(mut self, c: char, seconds: f32) -> Self {
+        self.pauses.insert(c, seconds);
+        self
+    }
+
+    /// Starts revealing the text from the beginning again.
+    pub fn restart(&mut self) {
+        self.revealed = 0;
+        self.cooldown = 0.0;
+    }
+
+    /// How long to wait after revealing `cluster`.
+    fn delay_after(&self, cluster: &str) -> f32 {
+        let pause = cluster
+            .chars()
+            .next_back()
+            .and_then(|c| self.pauses.get(&c))
+            .copied()
+            .unwrap_or(0.0);
+        self.seconds_per_char + pause
+    }
+}
+
+/// Sent by [`reveal_text`] whenever a [`TypewriterReveal`] reveals part of
+/// its text.
+#[derive(Debug, Clone, Event)]
+pub struct CharacterRevealed {
+    /// The entity whose text it is.
+    pub entity: Entity,
+    /// Index of the grapheme cluster that was revealed.
+    pub index: usize,
+    /// The grapheme cluster that was revealed. This includes spaces and
+    /// newlines, so you might want to skip those when playing sounds.
+    pub cluster: String,
+}
+
+/// System that advances each [`TypewriterReveal`].
+pub fn reveal_text(
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut ImageFontText, &mut TypewriterReveal)>,
+    mut events: EventWriter<CharacterRevealed>,
+) {
+    for (entity, mut image_font_text, mut reveal) in &mut query {
+        let cache = reveal
+            .clusters
+            .take()
+            .filter(|cache| cache.is_for(&image_font_text))
+            .unwrap_or_else(|| ClusterCache::new(&image_font_text));
+        let clusters = &cache.clusters;
+
+        reveal.cooldown -= time.delta_seconds();
+        while reveal.cooldown <= 0.0 && reveal.revealed < clusters.len() {
+            let cluster = &clusters[reveal.revealed];
+            events.send(CharacterRevealed {
+                entity,
+                index: reveal.revealed,
+                cluster: cluster.clone(),
+            });
+            reveal.revealed += 1;
+            reveal.cooldown += reveal.delay_after(cluster);
+        }
+        // don't build up time to spend once there's new text to reveal
+        reveal.cooldown = reveal.cooldown.max(0.0);
+
+        let visible_chars = Some(reveal.revealed.min(clusters.len()));
+        reveal.clusters = Some(cache);
+        // only touch the text when needed, since that rerenders it
+        if image_font_text.visible_chars != visible_chars {
+            image_font_text.visible_chars = visible_chars;
+        }
+    }
+}
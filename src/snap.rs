@@ -0,0 +1,88 @@
+//! Snapping text sprites to the pixel grid, so that they render crisply.
+use bevy::prelude::*;
+
+/// Add this to an entity with an [`ImageFontBundle`] to keep its text crisp
+/// wherever it is.
+///
+/// Text rendered 'in the world' is only pixel-perfect if each of its pixels
+/// lines up with a pixel on the screen. Whether it does depends on the
+/// sprite's anchor, whether the image's width and height are odd or even, and
+/// the camera's position and scale, so placing text at whole-number
+/// coordinates isn't enough. [`snap_to_pixels`] moves the entity's
+/// [`GlobalTransform`] (but not its [`Transform`]) so that the sprite's
+/// corner lands exactly on a pixel boundary of the first active
+/// orthographic camera.
+///
+/// Rotation is ignored, and the entity's children aren't moved along with
+/// it.
+///
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use extol_image_font::{snap::PixelSnap, ImageFontBundle};
+/// # fn spawn(mut commands: Commands) {
+/// commands.spawn((ImageFontBundle::default(), PixelSnap::default()));
+/// # }
+/// ```
+///
+/// [`ImageFontBundle`]: crate::ImageFontBundle
+#[derive(Debug, Clone, Copy, Default, Reflect, Component)]
+pub struct PixelSnap {
+    /// Where the entity is before snapping. Transform propagation only
+    /// updates the [`GlobalTransform`] when the entity moves, so otherwise
+    /// this is what gets snapped, rather than last frame's snapped position.
+    #[reflect(ignore)]
+    unsnapped: Option<GlobalTransform>,
+}
+
+/// System that snaps each entity with a [`PixelSnap`] to the pixel grid.
+pub fn snap_to_pixels(
+    cameras: Query<(&Camera, &OrthographicProjection, &GlobalTransform), Without<PixelSnap>>,
+    mut query: Query<(
+        &mut GlobalTransform,
+        &mut PixelSnap,
+        &Sprite,
+        &Handle<Image>,
+    )>,
+    images: Res<Assets<Image>>,
+) {
+    let Some((camera, projection, camera_transform)) =
+        cameras.iter().find(|(camera, ..)| camera.is_active)
+    else {
+        return;
+    };
+    let Some(viewport_size) = camera.physical_viewport_size() else {
+        return;
+    };
+    // the size of a pixel on the screen in world units, and a point on the
+    // pixel grid (the bottom left corner of the viewport)
+    let pixel = projection.area.size() / viewport_size.as_vec2();
+    let grid_origin = camera_transform.translation().truncate() + projection.area.min;
+
+    for (mut transform, mut snap, sprite, image) in &mut query {
+        // our own changes from last frame don't count as changes here
+        let unsnapped = match snap.unsnapped {
+            Some(unsnapped) if !transform.is_changed() => unsnapped,
+            _ => {
+                snap.unsnapped = Some(*transform);
+                *transform
+            }
+        };
+        let Some(size) = sprite
+            .custom_size
+            .or(sprite.rect.map(|rect| rect.size()))
+            .or_else(|| images.get(image).map(Image::size_f32))
+        else {
+            continue;
+        };
+        let (scale, rotation, translation) = unsnapped.to_scale_rotation_translation();
+        let size = size * scale.truncate();
+        // the sprite's bottom left corner
+        let corner = translation.truncate() - (sprite.anchor.as_vec() + 0.5) * size;
+        let snapped = grid_origin + ((corner - grid_origin) / pixel).round() * pixel;
+        *transform = GlobalTransform::from(Transform {
+            translation: translation + (snapped - corner).extend(0.0),
+            rotation,
+            scale,
+        });
+    }
+}